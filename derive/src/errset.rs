@@ -0,0 +1,204 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    braced,
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Attribute, Ident, Path, PathSegment, Token, Type,
+};
+
+use crate::metadata::{self, Entry, Metadata, Payload};
+
+/// An `errset!` invocation, along with the tree's metadata once its macro has added it.
+pub struct Input {
+    metadata: Option<Metadata>,
+    /// The invocation without `metadata`, for handing to the tree's metadata macro.
+    tokens: TokenStream,
+    attrs: Vec<Attribute>,
+    name: Ident,
+    root: Path,
+    /// The variants making up the subset, each named by the last few variants leading to it.
+    members: Vec<Punctuated<Ident, Token![::]>>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let metadata = if input.peek(Token![@]) { Some(input.parse()?) } else { None };
+        let tokens = input.fork().parse()?;
+        let attrs = input.call(Attribute::parse_outer)?;
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+
+        let mut root = Path { leading_colon: input.parse()?, segments: Punctuated::new() };
+        loop {
+            root.segments.push(PathSegment::from(Ident::parse_any(input)?));
+            input.parse::<Token![::]>()?;
+            if input.peek(syn::token::Brace) {
+                break;
+            }
+        }
+        let content;
+        braced!(content in input);
+        let members =
+            Punctuated::<_, Token![,]>::parse_terminated_with(&content, Punctuated::parse_separated_nonempty)?
+                .into_iter()
+                .collect();
+        Ok(Self { metadata, tokens, attrs, name, root, members })
+    }
+}
+
+/// The fields of `entry`'s variant, for declaring it.
+fn field_tys(root: &Path, metadata: &Metadata, entry: &Entry) -> Option<TokenStream> {
+    let extras = &metadata.extras;
+    match entry.payload {
+        Payload::Plain(i) | Payload::Mount(i, _) => {
+            let ty = metadata::alias(root, i);
+            Some(quote!((#ty #(, #extras)*)))
+        },
+        Payload::Shared(i) => {
+            let ty = metadata::alias(root, i);
+            Some(quote!((::std::sync::Arc<#ty> #(, #extras)*)))
+        },
+        Payload::Dyn => Some(quote!((::std::boxed::Box<dyn ::std::error::Error + Send + Sync> #(, #extras)*))),
+        Payload::Subtree(i) => {
+            let ty = metadata::alias(root, i);
+            Some(quote!((#ty)))
+        },
+        Payload::Flat => None,
+    }
+}
+
+/// Bindings for the fields of `entry`'s variant, usable both as a pattern and an expression.
+fn fields(metadata: &Metadata, entry: &Entry) -> Option<TokenStream> {
+    let count = match entry.payload {
+        Payload::Plain(_) | Payload::Mount(..) | Payload::Shared(_) | Payload::Dyn => 1 + metadata.extras.len(),
+        Payload::Subtree(_) => 1,
+        Payload::Flat => return None,
+    };
+    let fields = (0..count).map(|i| format_ident!("f{i}"));
+    Some(quote!((#(#fields),*)))
+}
+
+fn render(variants: &[Ident]) -> String {
+    variants.iter().map(ToString::to_string).collect::<Vec<_>>().join("::")
+}
+
+pub fn expand(input: Input) -> syn::Result<TokenStream> {
+    let Input { metadata, tokens, attrs, name, root, members } = input;
+    let Some(metadata) = metadata else {
+        return Ok(metadata::request(&root, quote!(::treeerror::expand_errset), TokenStream::new(), tokens));
+    };
+
+    let root_name = &root.segments.last().expect("paths aren't empty").ident;
+    let mut picked: Vec<&Entry> = Vec::new();
+    for member in &members {
+        let wanted = member.iter().cloned().collect::<Vec<_>>();
+        let found = metadata.entries.iter().filter(|entry| entry.variants.ends_with(&wanted)).collect::<Vec<_>>();
+        let entry = match found[..] {
+            [entry] => entry,
+            [] => {
+                let message = format!("`{root_name}` has no variant `{}`", render(&wanted));
+                return Err(syn::Error::new_spanned(member, message));
+            },
+            [first, second, ..] => {
+                let message = format!(
+                    "`{}` is ambiguous, it could be `{}` or `{}`",
+                    render(&wanted),
+                    render(&first.variants),
+                    render(&second.variants),
+                );
+                return Err(syn::Error::new_spanned(member, message));
+            },
+        };
+        for other in &picked {
+            let (short, long) =
+                if other.variants.len() < entry.variants.len() { (other, &entry) } else { (&entry, other) };
+            if long.variants.starts_with(&short.variants) {
+                let message = format!(
+                    "`{}` is already part of the set, through `{}`",
+                    render(&long.variants),
+                    render(&short.variants),
+                );
+                return Err(syn::Error::new_spanned(member, message));
+            }
+        }
+        picked.push(entry);
+    }
+
+    let variants = picked.iter().map(|entry| {
+        let (variant, fields) = (entry.variants.last(), field_tys(&root, &metadata, entry));
+        quote!(#variant #fields)
+    });
+    let mut out = quote! {
+        #(#attrs)*
+        #[allow(dead_code)]
+        pub enum #name {
+            #(#variants,)*
+        }
+    };
+
+    let (into_root, from_root): (Vec<_>, Vec<_>) = picked
+        .iter()
+        .map(|entry| {
+            let (variant, fields) = (entry.variants.last(), fields(&metadata, entry));
+            let nested = metadata::nested(&root, entry, fields.clone());
+            (quote!(#name::#variant #fields => #nested,), quote!(#nested => Ok(Self::#variant #fields),))
+        })
+        .unzip();
+    out.extend(quote! {
+        #[automatically_derived]
+        impl From<#name> for #root {
+            fn from(set: #name) -> Self {
+                match set {
+                    #(#into_root)*
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl TryFrom<#root> for #name {
+            type Error = #root;
+
+            fn try_from(root: #root) -> Result<Self, #root> {
+                #[allow(unreachable_patterns)]
+                match root {
+                    #(#from_root)*
+                    root => Err(root),
+                }
+            }
+        }
+    });
+
+    // Sources are converted into the root first, which fills in the extra fields the same way
+    // converting them anywhere else in the tree would. That includes the sources of everything
+    // below a subtree in the set, and of everything in a mounted tree.
+    for entry in &metadata.entries {
+        let Some(member) = picked.iter().find(|member| entry.variants.starts_with(&member.variants)) else {
+            continue;
+        };
+        let mounted = match &entry.payload {
+            Payload::Mount(_, mounted) => &mounted[..],
+            _ => &[],
+        };
+        for &source in entry.payload.source().iter().chain(mounted) {
+            let source: Type = metadata::alias(&root, source);
+            let (variant, fields) = (member.variants.last(), fields(&metadata, member));
+            let nested = metadata::nested(&root, member, fields.clone());
+            out.extend(quote! {
+                #[automatically_derived]
+                impl From<#source> for #name {
+                    #[track_caller]
+                    fn from(e: #source) -> Self {
+                        #[allow(unreachable_patterns)]
+                        match <#root>::from(e) {
+                            #nested => Self::#variant #fields,
+                            _ => unreachable!("converted into a different variant"),
+                        }
+                    }
+                }
+            });
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,87 @@
+//! Procedural macros for `treeerror`, which re-exports (and documents) them. The derives expand
+//! into invocations of its `macro_rules!` macros.
+
+mod errset;
+mod map_enum;
+mod metadata;
+mod shared_mappings;
+mod tree;
+mod tree_error;
+mod tree_pat;
+mod unique_sources;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Generates `From` impls onto the annotated enum, the same way `map_enum!` does.
+///
+/// See the `treeerror` crate for the attributes this accepts.
+#[proc_macro_derive(MapEnum, attributes(map_from, map))]
+pub fn derive_map_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    map_enum::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generates `From` impls for every `#[tree]` variant of the annotated enum, as well as
+/// transitive ones for everything below those variants.
+///
+/// See the `treeerror` crate for the attributes this accepts.
+#[proc_macro_derive(TreeError, attributes(tree))]
+pub fn derive_tree_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    tree_error::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Checks that no type in a `from_chain!` would be converted into the root more than once, which
+/// would otherwise surface as conflicting `From` impls.
+#[doc(hidden)]
+#[proc_macro]
+pub fn unique_sources(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as unique_sources::Input);
+    unique_sources::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Drops the shared mappings of a many-to-one `map_enum!` that an override replaces for one of
+/// its sources.
+#[doc(hidden)]
+#[proc_macro]
+pub fn shared_mappings(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as shared_mappings::Input);
+    shared_mappings::expand(input).into()
+}
+
+/// The implementation of `treeerror!`, which is documented in the main crate.
+#[doc(hidden)]
+#[proc_macro]
+pub fn expand_tree(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as tree::Input);
+    tree::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The implementation of `errset!`, which is documented in the main crate.
+#[doc(hidden)]
+#[proc_macro]
+pub fn expand_errset(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as errset::Input);
+    errset::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The implementation of `tree_pat!`, which is documented in the main crate.
+#[doc(hidden)]
+#[proc_macro]
+pub fn expand_tree_pat(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as tree_pat::Input);
+    tree_pat::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,169 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Data, DeriveInput, ExprClosure, Fields, Ident, Pat, Path, Token,
+};
+
+/// A `#[map_from(...)]` attribute on the target enum.
+struct Source {
+    path: Path,
+    flatten: Vec<Ident>,
+    catch: Option<ExprClosure>,
+}
+
+impl Parse for Source {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut flatten = Vec::new();
+        let mut catch = None;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            if key == "flatten" {
+                let content;
+                syn::parenthesized!(content in input);
+                flatten.extend(Punctuated::<Ident, Token![,]>::parse_terminated(&content)?);
+            } else if key == "catch" {
+                input.parse::<Token![=]>()?;
+                catch = Some(input.parse()?);
+            } else {
+                return Err(syn::Error::new_spanned(key, "expected `flatten(...)` or `catch = |e| ...`"));
+            }
+        }
+        Ok(Self { path, flatten, catch })
+    }
+}
+
+/// A `#[map(...)]` attribute on one of the target enum's variants.
+#[derive(Default)]
+struct Mapping {
+    source: Option<Path>,
+    from: Option<Ident>,
+    unit: bool,
+    conv: bool,
+    skip: bool,
+}
+
+impl Mapping {
+    fn parse(attr: &syn::Attribute) -> syn::Result<Self> {
+        let mut mapping = Self::default();
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("source") {
+                mapping.source = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("from") {
+                mapping.from = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("unit") {
+                mapping.unit = true;
+            } else if meta.path.is_ident("conv") {
+                mapping.conv = true;
+            } else if meta.path.is_ident("skip") {
+                mapping.skip = true;
+            } else {
+                return Err(meta.error("expected one of `source`, `from`, `unit`, `conv` or `skip`"));
+            }
+            Ok(())
+        })?;
+        Ok(mapping)
+    }
+
+    fn applies_to(&self, source: &Path) -> bool {
+        match &self.source {
+            Some(path) => path.to_token_stream().to_string() == source.to_token_stream().to_string(),
+            None => true,
+        }
+    }
+}
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(input, "`MapEnum` can only be derived for enums"));
+    };
+
+    let sources = input.attrs.iter()
+        .filter(|attr| attr.path().is_ident("map_from"))
+        .map(|attr| attr.parse_args::<Source>())
+        .collect::<syn::Result<Vec<_>>>()?;
+    if sources.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`MapEnum` needs at least one `#[map_from(...)]` attribute",
+        ));
+    }
+
+    let mut variants = Vec::new();
+    for variant in &data.variants {
+        let mut mappings = variant.attrs.iter()
+            .filter(|attr| attr.path().is_ident("map"))
+            .map(Mapping::parse)
+            .collect::<syn::Result<Vec<_>>>()?;
+        if mappings.is_empty() {
+            mappings.push(Mapping::default());
+        }
+        let params = match &variant.fields {
+            Fields::Unit => None,
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => Some(vec![]),
+            Fields::Unnamed(fields) => Some((0..fields.unnamed.len()).map(|i| format_ident!("__{i}")).collect()),
+            Fields::Named(_) => {
+                return Err(syn::Error::new_spanned(variant, "`MapEnum` does not support struct variants"));
+            },
+        };
+        variants.push((&variant.ident, params, mappings));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut impls = Vec::new();
+    for source in &sources {
+        let mut entries = Vec::new();
+        for (variant, params, mappings) in &variants {
+            for mapping in mappings.iter().filter(|m| !m.skip && m.applies_to(&source.path)) {
+                let unit = (mapping.unit || params.is_none()).then(|| quote!(@unit));
+                let conv = mapping.conv.then(|| quote!(@conv));
+                let from = mapping.from.as_ref().unwrap_or(variant);
+                let rename = (from != *variant).then(|| quote!(> #variant));
+                let params = match params {
+                    Some(params) if !params.is_empty() && unit.is_none() => Some(quote!(= (#(#params),*))),
+                    _ => None,
+                };
+                entries.push(quote!(#unit #conv #from #rename #params));
+            }
+        }
+        for flattened in &source.flatten {
+            entries.push(quote!(@flatten @conv #flattened));
+        }
+        if entries.is_empty() {
+            return Err(syn::Error::new_spanned(&source.path, "no variants are mapped from this source"));
+        }
+
+        let catch = source.catch.as_ref().map(catch_all).transpose()?;
+        let path = &source.path;
+        let header = if input.generics.params.is_empty() {
+            quote!(#path > #name)
+        } else {
+            quote!(impl #impl_generics #path > #name #ty_generics #where_clause)
+        };
+        impls.push(quote! {
+            ::treeerror::map_enum!(#header {
+                #(#entries),*
+            } #catch);
+        });
+    }
+
+    Ok(quote!(#(#impls)*))
+}
+
+/// Turns `catch = |e| ...` into the catch-all block `map_enum!` expects.
+fn catch_all(closure: &ExprClosure) -> syn::Result<TokenStream> {
+    let binding = match closure.inputs.iter().collect::<Vec<_>>().as_slice() {
+        [Pat::Ident(pat)] => pat.ident.clone(),
+        [Pat::Wild(_)] => format_ident!("_ignored"),
+        _ => return Err(syn::Error::new_spanned(closure, "expected a closure taking a single binding")),
+    };
+    let body = &closure.body;
+    Ok(quote!(|#binding| { #body }))
+}
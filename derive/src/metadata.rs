@@ -0,0 +1,310 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    bracketed,
+    ext::IdentExt,
+    parenthesized,
+    parse::{Parse, ParseStream},
+    parse_quote,
+    punctuated::Punctuated,
+    GenericArgument, Ident, LitInt, LitStr, Path, PathArguments, Token, Type,
+};
+
+/// What a tree's metadata macro adds in front of the invocation it's handed, as
+/// `@tree([enabled], [sources], [extras], [entries])`.
+///
+/// Types in the tree are referred to by their index among the aliases declared next to the root,
+/// since that's the only place they can be named from anywhere else. Sources and entries are
+/// written as `([conditions] item)`, with the `#[cfg(...)]` conditions they need as indices into
+/// `enabled`, which has a `1` for every condition that holds where the tree is declared and a `0`
+/// for the rest. Anything that needs a condition that doesn't hold is left out when parsing.
+pub struct Metadata {
+    /// The types converting into the root.
+    pub sources: Vec<Source>,
+    /// The types of the extra fields on leaves.
+    pub extras: Vec<Type>,
+    pub entries: Vec<Entry>,
+}
+
+/// A type converting into the root, written as `(index "path" [type])`.
+#[derive(Clone)]
+pub struct Source {
+    pub index: usize,
+    /// The nodes leading to it from the root, as `A > B`, for pointing at it in errors.
+    pub path: String,
+    /// The type, if it names the same thing wherever it's written, so that it can be compared to
+    /// types in the trees it's mounted into.
+    pub ty: Option<Type>,
+}
+
+/// A variant somewhere in the tree, other than behind a disabled `#[cfg(...)]`.
+#[derive(Clone)]
+pub struct Entry {
+    /// The variants leading to it from the root, ending with its own.
+    pub variants: Vec<Ident>,
+    /// The sources of the enums holding each of `variants` but the first, which the root holds.
+    pub holders: Vec<usize>,
+    pub payload: Payload,
+}
+
+#[derive(Clone)]
+pub enum Payload {
+    /// A leaf holding its source.
+    Plain(usize),
+    /// A leaf holding its source in an `Arc`.
+    Shared(usize),
+    /// A mounted tree's root, along with the sources of the mounted tree converting through it.
+    Mount(usize, Vec<usize>),
+    Dyn,
+    Flat,
+    /// An enum, holding nothing but itself.
+    Subtree(usize),
+}
+
+impl Parse for Metadata {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![@]>()?;
+        let ident: Ident = input.parse()?;
+        if ident != "tree" {
+            return Err(syn::Error::new_spanned(ident, "expected `@tree`"));
+        }
+        let content;
+        parenthesized!(content in input);
+        let flags;
+        bracketed!(flags in content);
+        let mut enabled = Vec::new();
+        while !flags.is_empty() {
+            enabled.push(flags.parse::<LitInt>()?.base10_parse::<u8>()? == 1);
+        }
+        content.parse::<Token![,]>()?;
+        let sources = parse_gated(&content, &enabled, Source::parse)?;
+        content.parse::<Token![,]>()?;
+        let extras;
+        bracketed!(extras in content);
+        let extras = Punctuated::<Type, Token![,]>::parse_terminated(&extras)?.into_iter().collect();
+        content.parse::<Token![,]>()?;
+        let entries = parse_gated(&content, &enabled, Entry::parse)?;
+        Ok(Self { sources, extras, entries })
+    }
+}
+
+/// A bracketed list of `([conditions] item)`, keeping the items whose conditions all hold.
+fn parse_gated<T>(
+    input: ParseStream,
+    enabled: &[bool],
+    parse: fn(ParseStream) -> syn::Result<T>,
+) -> syn::Result<Vec<T>> {
+    let list;
+    bracketed!(list in input);
+    let mut items = Vec::new();
+    while !list.is_empty() {
+        let (item, conditions);
+        parenthesized!(item in list);
+        bracketed!(conditions in item);
+        let mut kept = true;
+        while !conditions.is_empty() {
+            let condition = conditions.parse::<LitInt>()?;
+            match enabled.get(condition.base10_parse::<usize>()?) {
+                Some(holds) => kept &= holds,
+                None => return Err(syn::Error::new_spanned(condition, "unknown condition")),
+            }
+        }
+        let parsed = parse(&item)?;
+        if kept {
+            items.push(parsed);
+        }
+    }
+    Ok(items)
+}
+
+impl Metadata {
+    /// The metadata as the metadata macro writes it out, with `enabled` filled in as the flags
+    /// for the conditions, and the conditions needed by each source and entry given by
+    /// `sources` and `entries`.
+    pub fn gated(&self, enabled: TokenStream, sources: &[Vec<usize>], entries: &[Vec<usize>]) -> TokenStream {
+        let conditions = |needs: Option<&Vec<usize>>| {
+            let needs = needs.into_iter().flatten().map(|&i| Literal::usize_unsuffixed(i));
+            quote!([#(#needs)*])
+        };
+        let gated_sources = self.sources.iter().enumerate().map(|(i, source)| {
+            let needs = conditions(sources.get(i));
+            quote!((#needs #source))
+        });
+        let gated_entries = self.entries.iter().enumerate().map(|(i, entry)| {
+            let needs = conditions(entries.get(i));
+            quote!((#needs #entry))
+        });
+        let extras = &self.extras;
+        quote!(@tree([#enabled], [#(#gated_sources)*], [#(#extras),*], [#(#gated_entries)*]))
+    }
+}
+
+impl ToTokens for Metadata {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(self.gated(TokenStream::new(), &[], &[]));
+    }
+}
+
+impl Parse for Source {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        parenthesized!(content in input);
+        let index = content.parse::<LitInt>()?.base10_parse()?;
+        let path = content.parse::<LitStr>()?.value();
+        let ty;
+        bracketed!(ty in content);
+        let ty = if ty.is_empty() { None } else { Some(ty.parse()?) };
+        Ok(Self { index, path, ty })
+    }
+}
+
+impl ToTokens for Source {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self { index, path, ty } = self;
+        let index = Literal::usize_unsuffixed(*index);
+        tokens.extend(quote!((#index #path [#ty])));
+    }
+}
+
+impl Parse for Entry {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        parenthesized!(content in input);
+        let variants;
+        bracketed!(variants in content);
+        let mut parsed = Vec::new();
+        while !variants.is_empty() {
+            parsed.push(variants.parse()?);
+        }
+        let holders;
+        bracketed!(holders in content);
+        let mut indices = Vec::new();
+        while !holders.is_empty() {
+            indices.push(holders.parse::<LitInt>()?.base10_parse()?);
+        }
+        let kind = Ident::parse_any(&content)?;
+        let payload = if kind == "dyn" {
+            Payload::Dyn
+        } else if kind == "flat" {
+            Payload::Flat
+        } else {
+            let source = content.parse::<LitInt>()?.base10_parse()?;
+            if kind == "plain" {
+                Payload::Plain(source)
+            } else if kind == "mount" {
+                let mounted;
+                bracketed!(mounted in content);
+                let mut sources = Vec::new();
+                while !mounted.is_empty() {
+                    sources.push(mounted.parse::<LitInt>()?.base10_parse()?);
+                }
+                Payload::Mount(source, sources)
+            } else if kind == "shared" {
+                Payload::Shared(source)
+            } else {
+                Payload::Subtree(source)
+            }
+        };
+        Ok(Self { variants: parsed, holders: indices, payload })
+    }
+}
+
+impl ToTokens for Entry {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self { variants, holders, payload } = self;
+        let holders = holders.iter().map(|&i| Literal::usize_unsuffixed(i));
+        let source = payload.source().map(Literal::usize_unsuffixed);
+        let kind = match payload {
+            Payload::Plain(_) => quote!(plain),
+            Payload::Mount(..) => quote!(mount),
+            Payload::Shared(_) => quote!(shared),
+            Payload::Dyn => quote!(dyn),
+            Payload::Flat => quote!(flat),
+            Payload::Subtree(_) => quote!(subtree),
+        };
+        let mounted = match payload {
+            Payload::Mount(_, mounted) => {
+                let mounted = mounted.iter().map(|&i| Literal::usize_unsuffixed(i));
+                Some(quote!([#(#mounted)*]))
+            },
+            _ => None,
+        };
+        tokens.extend(quote!(([#(#variants)*] [#(#holders)*] #kind #source #mounted)));
+    }
+}
+
+impl Payload {
+    pub fn source(&self) -> Option<usize> {
+        match *self {
+            Self::Plain(i) | Self::Mount(i, _) | Self::Shared(i) | Self::Subtree(i) => Some(i),
+            Self::Dyn | Self::Flat => None,
+        }
+    }
+}
+
+/// Whether `ty` names the same type wherever it's written: paths starting at `::` or one of the
+/// standard crates, prelude types and primitives, and types built out of nothing else.
+pub fn is_global(ty: &Type) -> bool {
+    const PRELUDE: &[&str] = &[
+        "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+        "f32", "f64", "String", "Vec", "Box", "Option", "Result",
+    ];
+    match ty {
+        Type::Path(ty) if ty.qself.is_none() => {
+            let segments = &ty.path.segments;
+            let first = segments.first().map(|segment| segment.ident.to_string()).unwrap_or_default();
+            let rooted = ty.path.leading_colon.is_some()
+                || ["std", "core", "alloc"].contains(&first.as_str())
+                || (segments.len() == 1 && PRELUDE.contains(&first.as_str()));
+            rooted
+                && segments.iter().all(|segment| match &segment.arguments {
+                    PathArguments::None => true,
+                    PathArguments::AngleBracketed(args) => args.args.iter().all(|arg| match arg {
+                        GenericArgument::Type(ty) => is_global(ty),
+                        GenericArgument::Lifetime(_) => true,
+                        _ => false,
+                    }),
+                    PathArguments::Parenthesized(_) => false,
+                })
+        },
+        Type::Reference(ty) => is_global(&ty.elem),
+        Type::Slice(ty) => is_global(&ty.elem),
+        Type::Paren(ty) => is_global(&ty.elem),
+        Type::Tuple(ty) => ty.elems.iter().all(is_global),
+        _ => false,
+    }
+}
+
+/// Has the metadata macro of the root at `root` hand `rest` over to `callback`, behind `prefix`
+/// and the tree's metadata. The module the root is in is passed along, for the macro to find the
+/// rest of itself in.
+pub fn request(root: &Path, callback: TokenStream, prefix: TokenStream, rest: TokenStream) -> TokenStream {
+    let mut module = root.clone();
+    module.segments.pop();
+    quote!(#root! { @callback [#callback] [#prefix] [#module] #rest })
+}
+
+/// The alias next to a root for the type with index `i` in its tree.
+pub fn alias_name(root: &Ident, i: usize) -> Ident {
+    format_ident!("__treeerror_{}_S{i}", root.unraw())
+}
+
+/// The alias for the type with index `i` in the tree at `root`, which has to be the path the root
+/// was declared at.
+pub fn alias(root: &Path, i: usize) -> Type {
+    let mut path = root.clone();
+    let root = path.segments.pop().expect("paths aren't empty").into_value();
+    path.segments.push(alias_name(&root.ident, i).into());
+    parse_quote!(#path)
+}
+
+/// The root at `root` wrapped around `entry`'s variant, holding `fields`. Works both as a pattern
+/// and an expression.
+pub fn nested(root: &Path, entry: &Entry, fields: Option<TokenStream>) -> TokenStream {
+    let holders =
+        std::iter::once(root.to_token_stream()).chain(entry.holders.iter().map(|&i| alias(root, i).to_token_stream()));
+    let steps = holders.zip(&entry.variants).collect::<Vec<_>>();
+    let ((holder, variant), outer) = steps.split_last().expect("entries have at least one variant");
+    let inner = quote!(#holder::#variant #fields);
+    outer.iter().rev().fold(inner, |inner, (holder, variant)| quote!(#holder::#variant(#inner)))
+}
@@ -0,0 +1,60 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    bracketed,
+    parse::{Parse, ParseStream},
+    Ident, Token,
+};
+
+/// Input to `shared_mappings!`, as handed over by a many-to-one `map_enum!`:
+/// `@callback [cb] [prefix] [[override]...] [variant] [mapping]...`.
+///
+/// Overrides are the variant they map followed by anything that makes them match only part of
+/// it, a nested pattern or a guard.
+pub struct Input {
+    callback: TokenStream,
+    prefix: TokenStream,
+    overrides: Vec<(Ident, bool)>,
+    shared: Vec<(Ident, TokenStream)>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![@]>()?;
+        input.parse::<Ident>()?;
+        let (callback, prefix);
+        bracketed!(callback in input);
+        bracketed!(prefix in input);
+
+        let content;
+        bracketed!(content in input);
+        let mut overrides = Vec::new();
+        while !content.is_empty() {
+            let entry;
+            bracketed!(entry in content);
+            let variant = entry.parse()?;
+            let partial = !entry.is_empty();
+            entry.parse::<TokenStream>()?;
+            overrides.push((variant, partial));
+        }
+
+        let mut shared = Vec::new();
+        while !input.is_empty() {
+            let (variant, mapping);
+            bracketed!(variant in input);
+            bracketed!(mapping in input);
+            shared.push((variant.parse()?, mapping.parse()?));
+        }
+        Ok(Self { callback: callback.parse()?, prefix: prefix.parse()?, overrides, shared })
+    }
+}
+
+/// Hands back the shared mappings that no override covers completely, since those would only
+/// ever be unreachable.
+pub fn expand(Input { callback, prefix, overrides, shared }: Input) -> TokenStream {
+    let kept = shared
+        .into_iter()
+        .filter(|(variant, _)| !overrides.iter().any(|(over, partial)| over == variant && !partial))
+        .map(|(_, mapping)| mapping);
+    quote!(#callback! { #prefix [#(#kept)*] })
+}
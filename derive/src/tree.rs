@@ -0,0 +1,1170 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    braced,
+    ext::IdentExt,
+    parenthesized,
+    parse::{Parse, ParseStream},
+    parse_quote,
+    punctuated::Punctuated,
+    AttrStyle, Attribute, Ident, LitStr, Meta, Path, Token, Type,
+};
+
+use crate::{
+    metadata::{self, Entry, Metadata, Payload},
+    unique_sources::{self, Source},
+};
+
+/// A `treeerror!` invocation.
+pub struct Input {
+    /// The metadata of each `@mount`ed tree resolved so far, in order, which their metadata
+    /// macros add in front of the invocation.
+    mounted: Vec<Metadata>,
+    /// The invocation without `mounted`, for handing to the next metadata macro.
+    tokens: TokenStream,
+    /// Attributes from the `#![...]` header, for every type in the invocation.
+    inherited: Vec<Attribute>,
+    roots: Vec<Node>,
+}
+
+/// Something stored next to the payload of every leaf, turned on by a setting on the root.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Extra {
+    Location,
+    Backtrace,
+    Context,
+}
+
+impl Extra {
+    fn from_modifier(modifier: &Ident) -> Option<Self> {
+        if modifier == "location" {
+            Some(Self::Location)
+        } else if modifier == "backtrace" {
+            Some(Self::Backtrace)
+        } else if modifier == "context" {
+            Some(Self::Context)
+        } else {
+            None
+        }
+    }
+
+    fn ty(self) -> TokenStream {
+        match self {
+            Self::Location => quote!(&'static ::core::panic::Location<'static>),
+            Self::Backtrace => quote!(::std::backtrace::Backtrace),
+            Self::Context => quote!(::treeerror::ContextStack),
+        }
+    }
+
+    /// Evaluated in the (`#[track_caller]`) `From` impl converting into the leaf.
+    fn init(self) -> TokenStream {
+        match self {
+            Self::Location => quote!(::core::panic::Location::caller()),
+            Self::Backtrace => quote!(::std::backtrace::Backtrace::capture()),
+            Self::Context => quote!(::treeerror::ContextStack::new()),
+        }
+    }
+}
+
+/// Settings on a root, which apply to the whole tree.
+#[derive(Default)]
+struct Settings {
+    extras: Vec<Extra>,
+    /// Whether each enum gets its own module, from `@modules`.
+    modules: bool,
+    /// The template for the names of generated types, from `@names("...")`, with `{}` standing
+    /// in for the node's name.
+    names: Option<LitStr>,
+    /// Whether the tree can be mounted into other trees, from `@export`.
+    export: bool,
+}
+
+impl Settings {
+    fn is_empty(&self) -> bool {
+        self.extras.is_empty() && !self.modules && self.names.is_none() && !self.export
+    }
+}
+
+struct Node {
+    /// Attributes for the node's own type.
+    attrs: Vec<Attribute>,
+    /// Whether the header's attributes apply to the node's type, turned off by `#[no_inherit]`.
+    inherit: bool,
+    /// Attributes for the node's variant in its parent, from `#[variant(...)]`.
+    variant_attrs: Vec<Meta>,
+    name: Ident,
+    /// Whether the node's children are flattened into its parent, from `@inline`.
+    inline: bool,
+    /// Only allowed on roots.
+    settings: Settings,
+    kind: Kind,
+}
+
+enum Kind {
+    Enum(Vec<Node>),
+    Unit,
+    FlatUnit,
+    Dyn,
+    Wrapped(Type),
+    /// A wrapped type that's kept in an `Arc`, from `@shared(...)`.
+    Shared(Type),
+    /// A tree exported from somewhere else, wrapped like a leaf. `sources` is filled in with the
+    /// types converting into it once its metadata macro has been expanded.
+    Mount { path: Path, sources: Option<Vec<metadata::Source>> },
+}
+
+/// A type converting into the root, as gathered from the tree.
+struct Gathered<'n> {
+    ty: Type,
+    /// The nodes leading to it, starting at the root.
+    path: String,
+    /// What to compare it to when looking for duplicates, which for types from a mounted tree is
+    /// only known if they name the same thing everywhere.
+    compared: Option<Type>,
+    cfgs: Vec<&'n Attribute>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut mounted = Vec::new();
+        while input.peek(Token![@]) {
+            mounted.push(input.parse::<Metadata>()?);
+        }
+        let tokens = input.fork().parse()?;
+
+        let mut inherited = input.call(Attribute::parse_inner)?;
+        for attr in &mut inherited {
+            attr.style = AttrStyle::Outer;
+        }
+        let mut roots = Punctuated::<Node, Token![,]>::parse_terminated(input)?.into_iter().collect::<Vec<_>>();
+        resolve_mounts(&mut roots, &mut mounted.iter().map(|metadata| metadata.sources.clone()));
+        for root in &roots {
+            if root.inline {
+                return Err(syn::Error::new_spanned(&root.name, "roots can't be `@inline`"));
+            }
+            if !root.settings.is_empty() && !matches!(root.kind, Kind::Enum(_)) {
+                return Err(syn::Error::new_spanned(&root.name, "settings can only be used on roots with children"));
+            }
+            if let Kind::Enum(children) = &root.kind {
+                reject_settings(children)?;
+            }
+        }
+        Ok(Self { mounted, tokens, inherited, roots })
+    }
+}
+
+fn resolve_mounts(nodes: &mut [Node], mounted: &mut impl Iterator<Item = Vec<metadata::Source>>) {
+    for node in nodes {
+        match &mut node.kind {
+            Kind::Enum(children) => resolve_mounts(children, mounted),
+            Kind::Mount { sources, .. } => *sources = mounted.next(),
+            _ => {},
+        }
+    }
+}
+
+/// `ty` pointing at `at`, for errors about types that come from somewhere else.
+fn respan(ty: &Type, at: &Path) -> Type {
+    let span = at.segments.last().map_or_else(Span::call_site, |segment| segment.ident.span());
+    let tokens = ty.to_token_stream().into_iter().map(|mut token| {
+        token.set_span(span);
+        token
+    });
+    parse_quote!(#(#tokens)*)
+}
+
+/// The first `@mount` that still needs its metadata.
+fn unresolved_mount(nodes: &[Node]) -> Option<&Path> {
+    nodes.iter().find_map(|node| match &node.kind {
+        Kind::Enum(children) => unresolved_mount(children),
+        Kind::Mount { path, sources: None } => Some(path),
+        _ => None,
+    })
+}
+
+fn reject_settings(nodes: &[Node]) -> syn::Result<()> {
+    for node in nodes {
+        if !node.settings.is_empty() {
+            return Err(syn::Error::new_spanned(&node.name, "settings can only be used on roots"));
+        }
+        if let Kind::Enum(children) = &node.kind {
+            reject_settings(children)?;
+        }
+    }
+    Ok(())
+}
+
+impl Parse for Node {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attrs = Vec::new();
+        let mut variant_attrs = Vec::new();
+        let mut inherit = true;
+        for attr in input.call(Attribute::parse_outer)? {
+            if attr.path().is_ident("no_inherit") {
+                attr.meta.require_path_only()?;
+                inherit = false;
+            } else if attr.path().is_ident("variant") {
+                variant_attrs.extend(attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?);
+            } else {
+                attrs.push(attr);
+            }
+        }
+        let name: Ident = input.parse()?;
+
+        let mut settings = Settings::default();
+        let mut modifier = None;
+        let mut mount = None;
+        let mut shared = None;
+        let mut inline = false;
+        while input.peek(Token![@]) {
+            input.parse::<Token![@]>()?;
+            let ident = Ident::parse_any(input)?;
+            if let Some(extra) = Extra::from_modifier(&ident) {
+                if settings.extras.contains(&extra) {
+                    return Err(syn::Error::new_spanned(ident, "duplicate setting"));
+                }
+                settings.extras.push(extra);
+            } else if ident == "modules" {
+                if settings.modules {
+                    return Err(syn::Error::new_spanned(ident, "duplicate setting"));
+                }
+                settings.modules = true;
+            } else if ident == "export" {
+                if settings.export {
+                    return Err(syn::Error::new_spanned(ident, "duplicate setting"));
+                }
+                settings.export = true;
+            } else if ident == "inline" {
+                if inline {
+                    return Err(syn::Error::new_spanned(ident, "duplicate modifier"));
+                }
+                inline = true;
+            } else if ident == "names" {
+                if settings.names.is_some() {
+                    return Err(syn::Error::new_spanned(ident, "duplicate setting"));
+                }
+                let content;
+                parenthesized!(content in input);
+                let template: LitStr = content.parse()?;
+                let value = template.value();
+                if value.matches("{}").count() != 1 || syn::parse_str::<Ident>(&value.replace("{}", "X")).is_err() {
+                    return Err(syn::Error::new_spanned(
+                        template,
+                        "expected a template like \"{}Error\", with a single `{}` for the node's name",
+                    ));
+                }
+                settings.names = Some(template);
+            } else if ["unit", "flatunit", "dyn", "mount", "shared"].iter().any(|kind| ident == kind) {
+                if modifier.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        ident,
+                        "nodes can only have one of `@unit`, `@flatunit`, `@dyn`, `@mount` or `@shared`",
+                    ));
+                }
+                if ident == "mount" {
+                    let content;
+                    parenthesized!(content in input);
+                    mount = Some(content.parse()?);
+                } else if ident == "shared" {
+                    let content;
+                    parenthesized!(content in input);
+                    shared = Some(content.parse()?);
+                }
+                modifier = Some(ident);
+            } else {
+                return Err(syn::Error::new_spanned(ident, "unknown modifier"));
+            }
+        }
+
+        let kind = if input.peek(syn::token::Brace) || input.peek(syn::token::Paren) {
+            if let Some(modifier) = modifier {
+                return Err(syn::Error::new_spanned(
+                    modifier,
+                    format!("`{name}` can't have both a modifier and children or a wrapped type"),
+                ));
+            }
+            if input.peek(syn::token::Brace) {
+                let content;
+                braced!(content in input);
+                Kind::Enum(Punctuated::<Node, Token![,]>::parse_terminated(&content)?.into_iter().collect())
+            } else {
+                let content;
+                parenthesized!(content in input);
+                Kind::Wrapped(content.parse()?)
+            }
+        } else {
+            match modifier {
+                Some(modifier) if modifier == "unit" => Kind::Unit,
+                Some(modifier) if modifier == "flatunit" => Kind::FlatUnit,
+                Some(modifier) if modifier == "dyn" => Kind::Dyn,
+                Some(modifier) if modifier == "mount" => Kind::Mount {
+                    path: mount.expect("`@mount` takes a path"),
+                    sources: None,
+                },
+                Some(_) => Kind::Shared(shared.expect("`@shared` takes a type")),
+                None => return Err(syn::Error::new_spanned(
+                    &name,
+                    format!("`{name}` needs children, a wrapped type, or one of `@unit`, `@flatunit`, `@dyn`, `@mount` or `@shared`"),
+                )),
+            }
+        };
+
+        if inline && !matches!(kind, Kind::Enum(_)) {
+            return Err(syn::Error::new_spanned(&name, "only nodes with children can be `@inline`"));
+        }
+        if inline && !variant_attrs.is_empty() {
+            return Err(syn::Error::new_spanned(&name, "`@inline` nodes don't have a variant to put attributes on"));
+        }
+
+        Ok(Self { attrs, inherit, variant_attrs, name, inline, settings, kind })
+    }
+}
+
+impl Node {
+    /// The node's `#[cfg(...)]`s, which also gate its variant, and everything below it or
+    /// converting from it.
+    fn cfgs(&self) -> impl Iterator<Item = &Attribute> {
+        self.attrs.iter().filter(|attr| attr.path().is_ident("cfg"))
+    }
+}
+
+/// `OtherWorld` → `other_world`, keeping acronyms together (`IOError` → `io_error`).
+fn snake_case(name: &Ident) -> Ident {
+    let name = name.to_string();
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i != 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    match snake.as_str() {
+        "self" | "super" | "crate" => format_ident!("{snake}_"),
+        _ => syn::parse_str(&snake).unwrap_or_else(|_| Ident::new_raw(&snake, Span::call_site())),
+    }
+}
+
+/// A variant of an enum, which is either one of its children or, for `@inline` children, one of
+/// theirs.
+struct Flat<'a> {
+    node: &'a Node,
+    /// Where the node is declared, relative to the enum's module.
+    scope: Vec<Ident>,
+    /// The `#[cfg(...)]`s of the `@inline` nodes in between.
+    cfgs: Vec<&'a Attribute>,
+}
+
+fn flatten(children: &[Node], modules: bool) -> Vec<Flat<'_>> {
+    fn go<'a>(children: &'a [Node], modules: bool, scope: &[Ident], cfgs: &[&'a Attribute], out: &mut Vec<Flat<'a>>) {
+        for node in children {
+            match &node.kind {
+                Kind::Enum(children) if node.inline => {
+                    let mut scope = scope.to_vec();
+                    if modules {
+                        scope.push(snake_case(&node.name));
+                    }
+                    go(children, modules, &scope, &[cfgs, &node.cfgs().collect::<Vec<_>>()].concat(), out);
+                },
+                _ => out.push(Flat { node, scope: scope.to_vec(), cfgs: cfgs.to_vec() }),
+            }
+        }
+    }
+    let mut out = Vec::new();
+    go(children, modules, &[], &[], &mut out);
+    out
+}
+
+/// The `@shared` leaves anywhere below `nodes`, along with the `#[cfg(...)]`s of the nodes
+/// leading to them, theirs included.
+fn shared_leaves<'a>(nodes: &'a [Node], cfgs: &[&'a Attribute], out: &mut Vec<(&'a Node, Vec<&'a Attribute>)>) {
+    for node in nodes {
+        let cfgs = [cfgs, &node.cfgs().collect::<Vec<_>>()].concat();
+        match &node.kind {
+            Kind::Enum(children) => shared_leaves(children, &cfgs, out),
+            Kind::Shared(_) => out.push((node, cfgs)),
+            _ => {},
+        }
+    }
+}
+
+/// Settings shared by everything in a single tree.
+struct Tree<'a> {
+    inherited: &'a [Attribute],
+    settings: &'a Settings,
+}
+
+impl Tree<'_> {
+    fn extra_tys(&self) -> impl Iterator<Item = TokenStream> + '_ {
+        self.settings.extras.iter().map(|extra| extra.ty())
+    }
+
+    fn extra_inits(&self) -> impl Iterator<Item = TokenStream> + '_ {
+        self.settings.extras.iter().map(|extra| extra.init())
+    }
+
+    /// The attributes for the type generated for `node`.
+    fn attrs<'a>(&'a self, node: &'a Node) -> impl Iterator<Item = &'a Attribute> {
+        let inherited = if node.inherit { self.inherited } else { &[] };
+        inherited.iter().chain(&node.attrs)
+    }
+
+    /// The name of the type generated for `node`, which its variant in the parent doesn't share
+    /// when there's a naming template.
+    fn ty_name(&self, node: &Node) -> Ident {
+        match &self.settings.names {
+            Some(template) => {
+                let name = template.value().replace("{}", &node.name.to_string());
+                format_ident!("{name}", span = node.name.span())
+            },
+            None => node.name.clone(),
+        }
+    }
+
+    /// The module the children of `node` are declared in, if `node` is declared in `scope`.
+    fn scope(&self, node: &Node, scope: &[Ident]) -> Vec<Ident> {
+        let mut scope = scope.to_vec();
+        if self.settings.modules {
+            scope.push(snake_case(&node.name));
+        }
+        scope
+    }
+
+    /// The type `node` gets converted from, if any, as seen from the invoking module when the
+    /// node is declared in `scope`.
+    fn source(&self, node: &Node, scope: &[Ident]) -> Option<Type> {
+        let name = &self.ty_name(node);
+        match &node.kind {
+            Kind::Enum(_) => {
+                let scope = self.scope(node, scope);
+                Some(parse_quote!(#(#scope::)* #name))
+            },
+            Kind::Unit => Some(parse_quote!(#(#scope::)* #name)),
+            Kind::Wrapped(ty) | Kind::Shared(ty) => Some(ty.clone()),
+            Kind::Mount { path, .. } => Some(parse_quote!(#path)),
+            Kind::FlatUnit | Kind::Dyn => None,
+        }
+    }
+
+    /// Gathers the types converting into the root, and the variants leading to each node.
+    /// `variants` are the ones leading to `nodes` from the root, `holders` the indices of the
+    /// sources for the enums holding all but the first of them, and `cfgs` the `#[cfg(...)]`s of
+    /// the nodes above, which everything gathered is paired with.
+    fn collect<'n>(
+        &self,
+        nodes: &'n [Node],
+        scope: &[Ident],
+        parents: &str,
+        (variants, holders, cfgs): (&[Ident], &[usize], &[&'n Attribute]),
+        sources: &mut Vec<Gathered<'n>>,
+        entries: &mut Vec<(Entry, Vec<&'n Attribute>)>,
+    ) {
+        for node in nodes {
+            let path = format!("{parents} > {}", node.name);
+            let cfgs = [cfgs, &node.cfgs().collect::<Vec<_>>()].concat();
+            let index = sources.len();
+            if let Some(ty) = self.source(node, scope) {
+                sources.push(Gathered { compared: Some(ty.clone()), ty, path: path.clone(), cfgs: cfgs.clone() });
+            }
+            let own = [variants, std::slice::from_ref(&node.name)].concat();
+            let payload = match &node.kind {
+                Kind::Enum(children) if node.inline => {
+                    let scope = self.scope(node, scope);
+                    self.collect(children, &scope, &path, (variants, holders, &cfgs), sources, entries);
+                    continue;
+                },
+                Kind::Enum(children) => {
+                    let scope = self.scope(node, scope);
+                    let holders = [holders, &[index]].concat();
+                    self.collect(children, &scope, &path, (&own, &holders, &cfgs), sources, entries);
+                    Payload::Subtree(index)
+                },
+                Kind::Mount { path: mount, sources: Some(mounted) } => {
+                    let first = sources.len();
+                    for source in mounted {
+                        sources.push(Gathered {
+                            ty: metadata::alias(mount, source.index),
+                            path: format!("{path} > {}", source.path),
+                            compared: source.ty.as_ref().map(|ty| respan(ty, mount)),
+                            cfgs: cfgs.clone(),
+                        });
+                    }
+                    Payload::Mount(index, (first..sources.len()).collect())
+                },
+                Kind::Unit | Kind::Wrapped(_) | Kind::Mount { .. } => Payload::Plain(index),
+                Kind::Shared(_) => Payload::Shared(index),
+                Kind::Dyn => Payload::Dyn,
+                Kind::FlatUnit => Payload::Flat,
+            };
+            entries.push((Entry { variants: own, holders: holders.to_vec(), payload }, cfgs));
+        }
+    }
+
+    /// Generates `node` and the nodes below it. Types are returned, to be declared in `scope`,
+    /// while impls go straight into `impls`, which end up in the invoking module. `holders` are
+    /// the enums with a variant for the node, nearest first, which is only ever more than the
+    /// parent for children of `@inline` nodes. `ancestors` are the enums above those, and `cfgs`
+    /// are the `#[cfg(...)]`s of all of them.
+    fn node(
+        &self,
+        node: &Node,
+        scope: &[Ident],
+        holders: &[Type],
+        ancestors: &[Type],
+        cfgs: &[&Attribute],
+        impls: &mut TokenStream,
+    ) -> TokenStream {
+        let (name, ty_name) = (&node.name, &self.ty_name(node));
+        let attrs = self.attrs(node);
+        let parent_cfgs = cfgs;
+        let cfgs = &[cfgs, &node.cfgs().collect::<Vec<_>>()].concat();
+        match &node.kind {
+            Kind::Enum(children) => {
+                let ty = self.source(node, scope).expect("enums are converted from");
+                let inner = self.scope(node, scope);
+                let flattened = flatten(children, self.settings.modules);
+                for &extra in &self.settings.extras {
+                    impls.extend(self.accessor(extra, &ty, &flattened, cfgs));
+                }
+                impls.extend(Self::shared_forwards(&ty, children, &flattened, cfgs));
+                let (holders, ancestors) = if node.inline {
+                    for holder in holders {
+                        impls.extend(self.inline_from(&ty, holder, &flattened, cfgs));
+                    }
+                    if let Some(top) = holders.last() {
+                        impls.extend(Self::ancestor_froms(&ty, top, ancestors, cfgs));
+                    }
+                    ([&[ty], holders].concat(), ancestors.to_vec())
+                } else {
+                    for holder in holders {
+                        impls.extend(quote! {
+                            #(#cfgs)*
+                            #[automatically_derived]
+                            impl From<#ty> for #holder {
+                                fn from(e: #ty) -> Self {
+                                    Self::#name(e)
+                                }
+                            }
+                        });
+                    }
+                    if let Some(top) = holders.last() {
+                        impls.extend(Self::ancestor_froms(&ty, top, ancestors, cfgs));
+                    }
+                    (vec![ty], [holders, ancestors].concat())
+                };
+
+                let variants = flattened.iter().map(|flat| self.variant(flat));
+                let mut items = quote! {
+                    #(#parent_cfgs)*
+                    #(#attrs)*
+                    #[allow(dead_code)]
+                    pub enum #ty_name {
+                        #(#variants,)*
+                    }
+                };
+                for child in children {
+                    items.extend(self.node(child, &inner, &holders, &ancestors, cfgs, impls));
+                }
+                match inner.last() {
+                    Some(module) if self.settings.modules => quote! {
+                        #(#cfgs)*
+                        pub mod #module {
+                            #[allow(unused_imports)]
+                            use super::*;
+
+                            #items
+                        }
+                    },
+                    _ => items,
+                }
+            },
+            Kind::Unit => {
+                let ty = self.source(node, scope).expect("unit leaves are converted from");
+                impls.extend(self.leaf_froms(&ty, &quote!(e), name, holders, ancestors, cfgs));
+                quote! {
+                    #(#parent_cfgs)*
+                    #(#attrs)*
+                    #[allow(dead_code)]
+                    pub struct #ty_name;
+                }
+            },
+            Kind::Wrapped(ty) => {
+                impls.extend(self.leaf_froms(ty, &quote!(e), name, holders, ancestors, cfgs));
+                TokenStream::new()
+            },
+            Kind::Shared(ty) => {
+                impls.extend(self.leaf_froms(ty, &quote!(::std::sync::Arc::new(e)), name, holders, ancestors, cfgs));
+                let getter = format_ident!("as_{}", snake_case(name).unraw());
+                let doc = format!("The payload of the `{name}` variant, if this is one.");
+                for holder in holders {
+                    impls.extend(quote! {
+                        #(#cfgs)*
+                        impl #holder {
+                            #[doc = #doc]
+                            #[allow(dead_code, unreachable_patterns)]
+                            pub fn #getter(&self) -> Option<&#ty> {
+                                match self {
+                                    Self::#name(e, ..) => Some(e),
+                                    _ => None,
+                                }
+                            }
+                        }
+                    });
+                }
+                TokenStream::new()
+            },
+            // Everything converting into the mounted root is converted the rest of the way
+            // through it.
+            Kind::Mount { path, sources } => {
+                let ty = parse_quote!(#path);
+                impls.extend(self.leaf_froms(&ty, &quote!(e), name, holders, ancestors, cfgs));
+                let ancestors = [holders, ancestors].concat();
+                for source in sources.as_ref().expect("mounts are resolved first") {
+                    let alias = metadata::alias(path, source.index);
+                    impls.extend(Self::ancestor_froms(&alias, &ty, &ancestors, cfgs));
+                }
+                TokenStream::new()
+            },
+            // Only the holders get the blanket impl, since it'd conflict with everything else
+            // converting into the nodes above them.
+            Kind::Dyn => {
+                for holder in holders {
+                    let inits = self.extra_inits();
+                    impls.extend(quote! {
+                        #(#cfgs)*
+                        #[automatically_derived]
+                        impl<E: ::std::error::Error + Send + Sync + 'static> From<E> for #holder {
+                            #[track_caller]
+                            fn from(e: E) -> Self {
+                                Self::#name(::std::boxed::Box::new(e) #(, #inits)*)
+                            }
+                        }
+                    });
+                }
+                TokenStream::new()
+            },
+            Kind::FlatUnit => TokenStream::new(),
+        }
+    }
+
+    /// `as_name()` for the `@shared` leaves in the subtrees of `name`, handing over to the
+    /// subtree holding the leaf. Names that are used by more than one leaf below `name` are left
+    /// out, since they'd clash.
+    fn shared_forwards(name: &Type, children: &[Node], flattened: &[Flat], cfgs: &[&Attribute]) -> TokenStream {
+        let mut all = Vec::new();
+        shared_leaves(children, &[], &mut all);
+        let getter = |leaf: &Node| format_ident!("as_{}", snake_case(&leaf.name).unraw());
+        let unique = |leaf: &Node| all.iter().filter(|(other, _)| getter(other) == getter(leaf)).count() == 1;
+
+        let mut out = TokenStream::new();
+        for flat in flattened {
+            let Kind::Enum(grandchildren) = &flat.node.kind else {
+                continue;
+            };
+            let path_cfgs = [&flat.cfgs[..], &flat.node.cfgs().collect::<Vec<_>>()].concat();
+            let mut leaves = Vec::new();
+            shared_leaves(grandchildren, &path_cfgs, &mut leaves);
+            for (leaf, leaf_cfgs) in leaves.into_iter().filter(|(leaf, _)| unique(leaf)) {
+                let Kind::Shared(ty) = &leaf.kind else {
+                    unreachable!("only `@shared` leaves are gathered");
+                };
+                let (getter, variant) = (getter(leaf), &flat.node.name);
+                let doc = format!("The payload of the `{}` variant further down, if this holds one.", leaf.name);
+                out.extend(quote! {
+                    #(#cfgs)*
+                    #(#leaf_cfgs)*
+                    impl #name {
+                        #[doc = #doc]
+                        #[allow(dead_code, unreachable_patterns)]
+                        pub fn #getter(&self) -> Option<&#ty> {
+                            match self {
+                                Self::#variant(node) => node.#getter(),
+                                _ => None,
+                            }
+                        }
+                    }
+                });
+            }
+        }
+        out
+    }
+
+    /// The variant for `flat` in the enum it's flattened into, as written in that enum's module.
+    fn variant(&self, flat: &Flat) -> TokenStream {
+        let Flat { node, scope, cfgs } = flat;
+        let Node { variant_attrs, name, .. } = node;
+        let (own_cfgs, ty_name) = (node.cfgs(), self.ty_name(node));
+        let extras = self.extra_tys();
+        let fields = match &node.kind {
+            Kind::Enum(_) => {
+                let module = self.scope(node, scope);
+                quote!((#(#module::)* #ty_name))
+            },
+            Kind::Unit => quote!((#(#scope::)* #ty_name #(, #extras)*)),
+            Kind::Wrapped(ty) => quote!((#ty #(, #extras)*)),
+            Kind::Shared(ty) => quote!((::std::sync::Arc<#ty> #(, #extras)*)),
+            Kind::Mount { path, .. } => quote!((#path #(, #extras)*)),
+            Kind::Dyn => quote!((::std::boxed::Box<dyn ::std::error::Error + Send + Sync> #(, #extras)*)),
+            Kind::FlatUnit => quote!(),
+        };
+        quote! {
+            #(#cfgs)*
+            #(#own_cfgs)*
+            #(#[#variant_attrs])*
+            #name #fields
+        }
+    }
+
+    /// Converts the `@inline` enum `ty` into one of the enums its variants were flattened into,
+    /// variant by variant.
+    fn inline_from(&self, ty: &Type, holder: &Type, flattened: &[Flat], cfgs: &[&Attribute]) -> TokenStream {
+        let arms = flattened.iter().map(|Flat { node, cfgs, .. }| {
+            let (own_cfgs, name) = (node.cfgs(), &node.name);
+            let fields = match node.kind {
+                Kind::Enum(_) => 1,
+                Kind::FlatUnit => 0,
+                Kind::Unit | Kind::Wrapped(_) | Kind::Shared(_) | Kind::Dyn | Kind::Mount { .. } => 1 + self.settings.extras.len(),
+            };
+            let fields = (0..fields).map(|i| format_ident!("f{i}")).collect::<Vec<_>>();
+            let fields = (!fields.is_empty()).then(|| quote!((#(#fields),*)));
+            quote! {
+                #(#cfgs)*
+                #(#own_cfgs)*
+                #ty::#name #fields => Self::#name #fields,
+            }
+        });
+        quote! {
+            #(#cfgs)*
+            #[automatically_derived]
+            impl From<#ty> for #holder {
+                fn from(e: #ty) -> Self {
+                    match e {
+                        #(#arms)*
+                    }
+                }
+            }
+        }
+    }
+
+    /// Leaves get the extra fields filled in when converting into them. `value` builds the
+    /// payload out of `e`.
+    fn leaf_froms(
+        &self,
+        ty: &Type,
+        value: &TokenStream,
+        name: &Ident,
+        holders: &[Type],
+        ancestors: &[Type],
+        cfgs: &[&Attribute],
+    ) -> TokenStream {
+        let mut out = TokenStream::new();
+        for holder in holders {
+            let inits = self.extra_inits();
+            out.extend(quote! {
+                #(#cfgs)*
+                #[automatically_derived]
+                impl From<#ty> for #holder {
+                    #[track_caller]
+                    fn from(e: #ty) -> Self {
+                        Self::#name(#value #(, #inits)*)
+                    }
+                }
+            });
+        }
+        if let Some(top) = holders.last() {
+            out.extend(Self::ancestor_froms(ty, top, ancestors, cfgs));
+        }
+        out
+    }
+
+    /// Converts into every ancestor by going through the direct parent, which is then converted
+    /// the rest of the way. The caller's location is passed through to the leaf.
+    fn ancestor_froms(ty: &Type, parent: &Type, ancestors: &[Type], cfgs: &[&Attribute]) -> TokenStream {
+        ancestors.iter().map(|goal| quote! {
+            #(#cfgs)*
+            #[automatically_derived]
+            impl From<#ty> for #goal {
+                #[track_caller]
+                fn from(e: #ty) -> Self {
+                    <#parent>::from(e).into()
+                }
+            }
+        }).collect()
+    }
+
+    /// Accessors for `extra` on the enum `name`, with a match arm per variant that's gated the
+    /// same way the variant is.
+    fn accessor(&self, extra: Extra, name: &Type, flattened: &[Flat], cfgs: &[&Attribute]) -> TokenStream {
+        let index = self.settings.extras.iter().position(|&e| e == extra).expect("accessors are only made for enabled extras");
+        let skipped = vec![quote!(_,); index];
+        let skipped = quote!(#(#skipped)*);
+        let mut leaves = Vec::new();
+        let mut subtrees = Vec::new();
+        let mut flats = Vec::new();
+        for Flat { node, cfgs, .. } in flattened {
+            let (own_cfgs, variant) = (node.cfgs(), &node.name);
+            let path = quote!(#(#cfgs)* #(#own_cfgs)* Self::#variant);
+            match node.kind {
+                Kind::Enum(_) => subtrees.push(path),
+                Kind::FlatUnit => flats.push(path),
+                Kind::Unit | Kind::Wrapped(_) | Kind::Shared(_) | Kind::Dyn | Kind::Mount { .. } => leaves.push(path),
+            }
+        }
+
+        match extra {
+            Extra::Location => quote! {
+                #(#cfgs)*
+                impl #name {
+                    /// Where the error was converted into the leaf holding it. Flat unit variants
+                    /// are created directly instead of being converted, so they don't have one.
+                    #[allow(dead_code)]
+                    pub fn location(&self) -> Option<&'static ::core::panic::Location<'static>> {
+                        match self {
+                            #(#leaves(_, #skipped location, ..) => Some(*location),)*
+                            #(#subtrees(node) => node.location(),)*
+                            #(#flats => None,)*
+                        }
+                    }
+                }
+            },
+            Extra::Backtrace => quote! {
+                #(#cfgs)*
+                impl #name {
+                    /// The backtrace captured when the error was converted into the leaf holding
+                    /// it, if capturing was enabled then.
+                    #[allow(dead_code)]
+                    pub fn backtrace(&self) -> Option<&::std::backtrace::Backtrace> {
+                        match self {
+                            #(#leaves(_, #skipped backtrace, ..) => match backtrace.status() {
+                                ::std::backtrace::BacktraceStatus::Captured => Some(backtrace),
+                                _ => None,
+                            },)*
+                            #(#subtrees(node) => node.backtrace(),)*
+                            #(#flats => None,)*
+                        }
+                    }
+                }
+            },
+            Extra::Context => quote! {
+                #(#cfgs)*
+                impl #name {
+                    /// The context added to the error on its way up the tree, innermost first.
+                    /// Flat unit variants have nowhere to keep it, so theirs is always empty.
+                    #[allow(dead_code)]
+                    pub fn context(&self) -> &::treeerror::ContextStack {
+                        match self {
+                            #(#leaves(_, #skipped context, ..) => context,)*
+                            #(#subtrees(node) => node.context(),)*
+                            #(#flats => ::treeerror::ContextStack::empty(),)*
+                        }
+                    }
+
+                    /// Like `context`, but mutable, and `None` for flat unit variants.
+                    #[allow(dead_code)]
+                    pub fn context_mut(&mut self) -> Option<&mut ::treeerror::ContextStack> {
+                        match self {
+                            #(#leaves(_, #skipped context, ..) => Some(context),)*
+                            #(#subtrees(node) => node.context_mut(),)*
+                            #(#flats => None,)*
+                        }
+                    }
+                }
+            },
+        }
+    }
+
+    /// The metadata `@mount`, `errset!` and `tree_pat!` need: aliases next to the root for every
+    /// type converting into it, and a macro named after the root that passes on their indices and
+    /// a description of the tree. With `@export`, the macro has to be exported from the crate
+    /// root, so its name there is made out of the root's name.
+    ///
+    /// `#[cfg(...)]`s have to be resolved here rather than wherever the macro is used, so the
+    /// macro goes through a chain of steps, one for each condition in the tree, each declared
+    /// twice behind the condition and its negation, which records whether it holds.
+    fn metadata(
+        &self,
+        root: &Node,
+        sources: &[Gathered],
+        entries: &[(Entry, Vec<&Attribute>)],
+    ) -> TokenStream {
+        let (name, cfgs) = (self.ty_name(root), root.cfgs().collect::<Vec<_>>());
+        let metadata = format_ident!("__treeerror_{name}");
+        let (export, visibility) = if self.settings.export {
+            (quote!(#[macro_export]), quote!(pub))
+        } else {
+            (quote!(#[allow(unused_macros)]), quote!(#[allow(unused_imports)] pub(crate)))
+        };
+        let gate = quote!(#(#cfgs)*);
+        let aliases = sources.iter().enumerate().map(|(i, Gathered { ty, cfgs, .. })| {
+            let alias = metadata::alias_name(&name, i);
+            quote! {
+                #gate
+                #(#cfgs)*
+                #[doc(hidden)]
+                #[allow(dead_code, non_camel_case_types)]
+                pub type #alias = #ty;
+            }
+        });
+
+        // Everything is gated on the indices of the conditions it needs.
+        let mut conditions = Vec::<TokenStream>::new();
+        let mut needs = |cfgs: &[&Attribute]| -> Vec<usize> {
+            let conditions = &mut conditions;
+            cfgs.iter()
+                .map(|attr| {
+                    let condition = attr.parse_args::<TokenStream>().unwrap_or_default();
+                    conditions.iter().position(|known| known.to_string() == condition.to_string()).unwrap_or_else(|| {
+                        conditions.push(condition);
+                        conditions.len() - 1
+                    })
+                })
+                .collect()
+        };
+        let source_needs = sources.iter().map(|source| needs(&source.cfgs)).collect::<Vec<_>>();
+        let entry_needs = entries.iter().map(|(_, cfgs)| needs(cfgs)).collect::<Vec<_>>();
+        let description = Metadata {
+            sources: sources
+                .iter()
+                .enumerate()
+                .map(|(index, source)| metadata::Source {
+                    index,
+                    path: source.path.split_once(" > ").map_or_else(String::new, |(_, path)| path.to_owned()),
+                    ty: source.compared.clone().filter(metadata::is_global),
+                })
+                .collect(),
+            extras: self.extra_tys().map(|ty| parse_quote!(#ty)).collect(),
+            entries: entries.iter().map(|(entry, _)| entry.clone()).collect(),
+        };
+        let describe = |enabled| {
+            let description = description.gated(enabled, &source_needs, &entry_needs);
+            quote!($($cb)*! { $($prefix)* #description $($rest)* })
+        };
+
+        // Steps are re-exported next to the root like it is, and found through the module the
+        // root was named in, which is handed over by whoever invoked the macro.
+        let steps = (0..conditions.len())
+            .map(|i| {
+                let name = name.unraw();
+                (format_ident!("__treeerror_{name}_C{i}"), format_ident!("__treeerror_{name}_step{i}"))
+            })
+            .collect::<Vec<_>>();
+        let step_path = |i: usize| {
+            let step = &steps[i].1;
+            quote!($($module)* #step)
+        };
+        let first = if conditions.is_empty() {
+            describe(TokenStream::new())
+        } else {
+            let next = step_path(0);
+            quote!(#next! { [$($cb)*] [$($prefix)*] [$($module)*] [] $($rest)* })
+        };
+        let mut macros = quote! {
+            #gate
+            #[doc(hidden)]
+            #export
+            macro_rules! #metadata {
+                (@callback [$($cb:tt)*] [$($prefix:tt)*] [$($module:tt)*] $($rest:tt)*) => {
+                    #first
+                };
+            }
+
+            #gate
+            #[doc(hidden)]
+            #visibility use #metadata as #name;
+        };
+        for (i, ((step, reexport), condition)) in steps.iter().zip(&conditions).enumerate() {
+            for (holds, cfg) in [(quote!(1), quote!(#condition)), (quote!(0), quote!(not(#condition)))] {
+                let body = if i + 1 < steps.len() {
+                    let next = step_path(i + 1);
+                    quote!(#next! { [$($cb)*] [$($prefix)*] [$($module)*] [$($enabled)* #holds] $($rest)* })
+                } else {
+                    describe(quote!($($enabled)* #holds))
+                };
+                macros.extend(quote! {
+                    #gate
+                    #[cfg(#cfg)]
+                    #[doc(hidden)]
+                    #export
+                    macro_rules! #step {
+                        ([$($cb:tt)*] [$($prefix:tt)*] [$($module:tt)*] [$($enabled:tt)*] $($rest:tt)*) => {
+                            #body
+                        };
+                    }
+                });
+            }
+            macros.extend(quote! {
+                #gate
+                #[doc(hidden)]
+                #visibility use #step as #reexport;
+            });
+        }
+        quote! {
+            #(#aliases)*
+
+            #macros
+        }
+    }
+
+    fn root_impls(&self, root: &Node) -> TokenStream {
+        if !self.settings.extras.contains(&Extra::Context) {
+            return TokenStream::new();
+        }
+        let (cfgs, impl_cfgs, root) = (root.cfgs(), root.cfgs(), self.ty_name(root));
+        quote! {
+            #(#cfgs)*
+            #[automatically_derived]
+            impl<E: Into<#root>> From<::treeerror::Context<E>> for #root {
+                #[track_caller]
+                fn from(context: ::treeerror::Context<E>) -> Self {
+                    let (error, stack) = context.into_parts();
+                    let mut node: Self = error.into();
+                    if let Some(context) = node.context_mut() {
+                        context.extend(stack);
+                    }
+                    node
+                }
+            }
+
+            #(#impl_cfgs)*
+            impl #root {
+                /// Moves the context off of the error and into a
+                /// [`Context`](::treeerror::Context) wrapping it, which renders the context in
+                /// its `Display` and `source()`.
+                #[allow(dead_code)]
+                pub fn into_context(mut self) -> ::treeerror::Context<Self> {
+                    let stack = self.context_mut().map(::core::mem::take).unwrap_or_default();
+                    ::treeerror::Context::new(self, stack)
+                }
+            }
+        }
+    }
+}
+
+pub fn expand(Input { mounted, tokens, inherited, roots }: Input) -> syn::Result<TokenStream> {
+    // Mounted trees are resolved one at a time, by handing the whole invocation to the metadata
+    // macro of the next one, which hands it back with its metadata added.
+    if let Some(path) = unresolved_mount(&roots) {
+        return Ok(metadata::request(path, quote!(::treeerror::expand_tree), quote!(#(#mounted)*), tokens));
+    }
+
+    let mut out = TokenStream::new();
+    for root in &roots {
+        let tree = Tree { inherited: &inherited, settings: &root.settings };
+        let (mut sources, mut entries) = (Vec::new(), Vec::new());
+        if let Kind::Enum(children) = &root.kind {
+            let name = tree.ty_name(root).to_string();
+            tree.collect(children, &tree.scope(root, &[]), &name, (&[], &[], &[]), &mut sources, &mut entries);
+            // Nodes behind a `cfg` are left out, since they might never be enabled at the same
+            // time as the nodes they'd clash with.
+            let sources = sources
+                .iter()
+                .filter(|source| source.cfgs.is_empty())
+                .filter_map(|source| Some(Source { ty: source.compared.as_ref()?, path: source.path.clone() }))
+                .collect::<Vec<_>>();
+            unique_sources::check(&name, &sources)?;
+        }
+
+        let mut impls = TokenStream::new();
+        out.extend(tree.node(root, &[], &[], &[], &[], &mut impls));
+        out.extend(impls);
+        // Only the root is re-exported, since the rest of the tree is allowed to reuse names.
+        if root.settings.modules {
+            let (cfgs, module, name) = (root.cfgs(), snake_case(&root.name), tree.ty_name(root));
+            out.extend(quote! {
+                #(#cfgs)*
+                pub use #module::#name;
+            });
+        }
+        out.extend(tree.root_impls(root));
+        if matches!(root.kind, Kind::Enum(_)) {
+            out.extend(tree.metadata(root, &sources, &entries));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use proc_macro2::TokenStream;
+    use quote::quote;
+
+    use super::{expand, Input};
+
+    fn expand_tree(tokens: TokenStream) -> syn::Result<TokenStream> {
+        expand(syn::parse2::<Input>(tokens)?)
+    }
+
+    #[test]
+    fn test_duplicate_sources() {
+        let error = expand_tree(quote! {
+            Root {
+                Message(String),
+                Config {
+                    Missing @unit,
+                    Path(String),
+                },
+                Missing @unit,
+            }
+        })
+        .unwrap_err();
+        let messages = error.into_iter().map(|error| error.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages, [
+            "`String` would be converted into `Root` twice, through `Root > Message` and through `Root > Config > Path`",
+            "`Missing` would be converted into `Root` twice, through `Root > Config > Missing` and through `Root > Missing`",
+        ]);
+    }
+
+    #[test]
+    fn test_duplicates_behind_cfg_allowed() {
+        expand_tree(quote! {
+            Root {
+                #[cfg(feature = "a")]
+                A(String),
+                #[cfg(not(feature = "a"))]
+                B(String),
+            }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_duplicate_through_mount() {
+        let error = expand_tree(quote! {
+            @tree([], [([] (0 "Io" [std::io::Error])) ([] (1 "Full" []))], [], [])
+            App {
+                Storage @mount(storage::Storage),
+                Io(std::io::Error),
+                Full @unit,
+            }
+        })
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`std :: io :: Error` would be converted into `App` twice, through `App > Storage > Io` and through \
+             `App > Io`",
+        );
+    }
+
+    #[test]
+    fn test_metadata_names_global_sources() {
+        let out = expand_tree(quote!(Storage { Io(std::io::Error), Full @unit })).unwrap().to_string();
+        assert!(out.contains(&quote!((0 "Io" [std::io::Error])).to_string()));
+        // `Full` is only a name in the module the tree is declared in.
+        assert!(out.contains(&quote!((1 "Full" [])).to_string()));
+    }
+
+    #[test]
+    fn test_metadata_steps_per_cfg_condition() {
+        let leaves = (0..12).map(|i| {
+            let (name, feature) = (quote::format_ident!("L{i}"), format!("f{}", i % 10));
+            quote!(#[cfg(feature = #feature)] #name @unit,)
+        });
+        let out = expand_tree(quote!(Root { #(#leaves)* })).unwrap().to_string();
+        // The root's macro, and one for each condition holding and not holding.
+        assert_eq!(out.matches("macro_rules !").count(), 1 + 2 * 10);
+    }
+}
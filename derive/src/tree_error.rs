@@ -0,0 +1,113 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, Ident, Path, Type, Visibility};
+
+/// A variant marked with `#[tree]`.
+struct Child<'a> {
+    variant: &'a Ident,
+    ty: &'a Type,
+    /// The macro generated by the child's own derive, unless the child is a `#[tree(leaf)]`.
+    node: Option<Path>,
+}
+
+/// Name of the macro a derived type exposes to its parents, kept apart from the metadata macros
+/// `treeerror!` roots get.
+fn node_macro(ident: &Ident) -> Ident {
+    format_ident!("__treeerror_derive_{}", ident)
+}
+
+fn child_macro(ty: &Type) -> syn::Result<Path> {
+    let Type::Path(ty) = ty else {
+        return Err(syn::Error::new_spanned(ty, "`#[tree]` variants need to wrap a path to a type, or be marked `#[tree(leaf)]`"));
+    };
+    let mut path = ty.path.clone();
+    let last = path.segments.last_mut().expect("paths have at least one segment");
+    last.ident = node_macro(&last.ident);
+    last.arguments = syn::PathArguments::None;
+    Ok(path)
+}
+
+fn parse_tree_attr(attr: &syn::Attribute) -> syn::Result<bool> {
+    if matches!(attr.meta, syn::Meta::Path(_)) {
+        return Ok(false);
+    }
+    let mut leaf = false;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("leaf") {
+            leaf = true;
+            Ok(())
+        } else {
+            Err(meta.error("expected `leaf`"))
+        }
+    })?;
+    Ok(leaf)
+}
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&input.generics, "`TreeError` does not support generic types"));
+    }
+
+    let mut children = Vec::new();
+    match &input.data {
+        Data::Enum(data) => for variant in &data.variants {
+            let Some(attr) = variant.attrs.iter().find(|attr| attr.path().is_ident("tree")) else {
+                continue;
+            };
+            let leaf = parse_tree_attr(attr)?;
+            let Fields::Unnamed(fields) = &variant.fields else {
+                return Err(syn::Error::new_spanned(variant, "`#[tree]` variants need to wrap exactly one type"));
+            };
+            let [field] = fields.unnamed.iter().collect::<Vec<_>>()[..] else {
+                return Err(syn::Error::new_spanned(variant, "`#[tree]` variants need to wrap exactly one type"));
+            };
+            let node = if leaf { None } else { Some(child_macro(&field.ty)?) };
+            children.push(Child { variant: &variant.ident, ty: &field.ty, node });
+        },
+        // Structs are leaves, they only exist so that parents can treat them like any other node.
+        Data::Struct(_) => {},
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(input, "`TreeError` cannot be derived for unions"));
+        },
+    }
+
+    let name = &input.ident;
+    let direct = children.iter().map(|Child { variant, ty, .. }| quote! {
+        ::treeerror::from!(#name = #variant(#ty));
+    });
+    let transitive = children.iter().filter_map(|Child { ty, node, .. }| node.as_ref().map(|node| quote! {
+        #node!(@froms #name, #ty);
+    }));
+
+    // Ancestors get `From` impls for every child of this node, via this node, and then recurse
+    // into the children that are nodes themselves.
+    let macro_name = node_macro(name);
+    let ancestor_froms = children.iter().map(|Child { ty, node, .. }| {
+        let recurse = node.as_ref().map(|node| quote! {
+            #node!(@froms $root, #ty);
+        });
+        quote! {
+            ::treeerror::from!($root = #ty > $node);
+            #recurse
+        }
+    });
+    // The macro can't be exported from the crate, so `pub` nodes only expose it to the crate.
+    let vis = match &input.vis {
+        Visibility::Public(_) => quote!(pub(crate)),
+        vis => quote!(#vis),
+    };
+
+    Ok(quote! {
+        #(#direct)*
+        #(#transitive)*
+
+        #[doc(hidden)]
+        macro_rules! #macro_name {
+            (@froms $root:ty, $node:ty) => {
+                #(#ancestor_froms)*
+            };
+        }
+        #[allow(unused_imports)]
+        #vis use #macro_name;
+    })
+}
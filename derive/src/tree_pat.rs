@@ -0,0 +1,74 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parenthesized,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Ident, Pat, Path, Token,
+};
+
+use crate::metadata::{self, Metadata, Payload};
+
+/// A `tree_pat!` invocation, along with the tree's metadata once its macro has added it.
+pub struct Input {
+    metadata: Option<Metadata>,
+    /// The invocation without `metadata`, for handing to the tree's metadata macro.
+    tokens: TokenStream,
+    root: Path,
+    /// The variants leading from the root to the one being matched.
+    variants: Vec<Ident>,
+    /// Patterns for the leading fields of the last variant.
+    fields: Option<Punctuated<Pat, Token![,]>>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let metadata = if input.peek(Token![@]) { Some(input.parse()?) } else { None };
+        let tokens = input.fork().parse()?;
+        let root = Path::parse_mod_style(input)?;
+        let mut variants = Vec::new();
+        while !input.is_empty() && !input.peek(syn::token::Paren) {
+            input.parse::<Token![>]>()?;
+            variants.push(input.parse()?);
+        }
+        if variants.is_empty() {
+            return Err(input.error("expected `>` and the variants leading to the one to match"));
+        }
+        let fields = if input.is_empty() {
+            None
+        } else {
+            let content;
+            parenthesized!(content in input);
+            Some(Punctuated::parse_terminated_with(&content, Pat::parse_multi_with_leading_vert)?)
+        };
+        Ok(Self { metadata, tokens, root, variants, fields })
+    }
+}
+
+pub fn expand(input: Input) -> syn::Result<TokenStream> {
+    let Input { metadata, tokens, root, variants, fields } = input;
+    let Some(metadata) = metadata else {
+        return Ok(metadata::request(&root, quote!(::treeerror::expand_tree_pat), TokenStream::new(), tokens));
+    };
+
+    let Some(entry) = metadata.entries.iter().find(|entry| entry.variants == variants) else {
+        let root_name = &root.segments.last().expect("paths aren't empty").ident;
+        let path = variants.iter().map(ToString::to_string).collect::<Vec<_>>().join(" > ");
+        let message = format!("`{root_name}` has no variant at `{root_name} > {path}`");
+        return Err(syn::Error::new_spanned(quote!(#(#variants)*), message));
+    };
+    // Everything past the given fields is skipped, which leaves extras like `@location` out of
+    // patterns unless they're asked for.
+    let fields = match (&entry.payload, fields) {
+        (Payload::Flat, None) => None,
+        (Payload::Flat, Some(fields)) => {
+            return Err(syn::Error::new_spanned(fields, "`@flatunit` variants don't have fields to match"));
+        },
+        (_, None) => Some(quote!((..))),
+        (_, Some(fields)) => {
+            let fields = fields.into_iter();
+            Some(quote!((#(#fields,)* ..)))
+        },
+    };
+    Ok(metadata::nested(&root, entry, fields))
+}
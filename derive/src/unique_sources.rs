@@ -0,0 +1,122 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{
+    bracketed,
+    parse::{Parse, ParseStream},
+    Ident, Token, Type,
+};
+
+/// Input to `unique_sources!`, as `@callback [cb] [prefix] [type]...` with the types of a
+/// `from_chain!` from the top of the chain to the bottom. The callback is only invoked if the
+/// check passes, so that a failing chain doesn't pile conflicting impls onto the error.
+pub struct Input {
+    callback: TokenStream,
+    prefix: TokenStream,
+    types: Vec<Type>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![@]>()?;
+        input.parse::<Ident>()?;
+        let (callback, prefix);
+        bracketed!(callback in input);
+        bracketed!(prefix in input);
+        let mut types = Vec::new();
+        while !input.is_empty() {
+            let content;
+            bracketed!(content in input);
+            types.push(content.parse()?);
+        }
+        Ok(Self { callback: callback.parse()?, prefix: prefix.parse()?, types })
+    }
+}
+
+/// A type that gets converted into the root, along with how it got there.
+pub struct Source<'a> {
+    pub ty: &'a Type,
+    pub path: String,
+}
+
+fn display(ty: &Type) -> String {
+    ty.to_token_stream().to_string()
+}
+
+/// What types are compared by, which doesn't tell `::std::...` apart from `std::...`.
+fn key(ty: &Type) -> String {
+    let ty = display(ty);
+    match ty.strip_prefix(":: ") {
+        Some(rest) if ["std ::", "core ::", "alloc ::"].iter().any(|krate| rest.starts_with(krate)) => rest.to_owned(),
+        _ => ty,
+    }
+}
+
+/// Errors for every source that shows up more than once, naming both paths leading to it.
+pub fn check(root: &str, sources: &[Source]) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    for (i, source) in sources.iter().enumerate() {
+        let (ty, key) = (display(source.ty), key(source.ty));
+        if let Some(first) = sources[..i].iter().find(|s| self::key(s.ty) == key) {
+            let error = syn::Error::new_spanned(
+                source.ty,
+                format!(
+                    "`{ty}` would be converted into `{root}` twice, through `{}` and through `{}`",
+                    first.path, source.path,
+                ),
+            );
+            match &mut errors {
+                Some(errors) => errors.combine(error),
+                None => errors = Some(error),
+            }
+        }
+    }
+    errors.map_or(Ok(()), Err)
+}
+
+pub fn expand(Input { callback, prefix, types }: Input) -> syn::Result<TokenStream> {
+    if let Some((root, rest)) = types.split_first() {
+        let mut path = display(root);
+        let sources = rest
+            .iter()
+            .map(|ty| {
+                path = format!("{path} > {}", display(ty));
+                Source { ty, path: path.clone() }
+            })
+            .collect::<Vec<_>>();
+        check(&display(root), &sources)?;
+    }
+    Ok(quote!(#callback! { #prefix }))
+}
+
+#[cfg(test)]
+mod test {
+    use quote::quote;
+
+    use super::{expand, Input};
+
+    fn expand_chain(tokens: proc_macro2::TokenStream) -> syn::Result<String> {
+        expand(syn::parse2::<Input>(tokens)?).map(|out| out.to_string())
+    }
+
+    #[test]
+    fn test_unique_chain() {
+        let out = expand_chain(quote!(@callback [from_chain] [@chain A : B, B] [A] [B] [C])).unwrap();
+        assert_eq!(out, quote!(from_chain! { @chain A : B, B }).to_string());
+    }
+
+    #[test]
+    fn test_repeated_link() {
+        let error = expand_chain(quote!(@callback [from_chain] [] [A] [B] [B] [C])).unwrap_err();
+        assert_eq!(error.to_string(), "`B` would be converted into `A` twice, through `A > B` and through `A > B > B`");
+    }
+
+    #[test]
+    fn test_every_repeat_reported() {
+        let error = expand_chain(quote!(@callback [from_chain] [] [A] [B] [C] [B] [C])).unwrap_err();
+        let messages = error.into_iter().map(|error| error.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages, [
+            "`B` would be converted into `A` twice, through `A > B` and through `A > B > C > B`",
+            "`C` would be converted into `A` twice, through `A > B > C` and through `A > B > C > B > C`",
+        ]);
+    }
+}
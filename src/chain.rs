@@ -63,14 +63,168 @@
 /// let a: A = C::from(D).into();
 /// let a: A = B::from(D).into();
 /// ```
+///
+/// When the variants are named after the types they wrap, the variant names can be left out.
+/// Any link can still name its variant explicitly with `Type as Variant`.
+/// ```
+/// use treeerror::from_chain;
+///
+/// struct D;
+///
+/// enum C {
+///     DVariant(D),
+/// }
+///
+/// enum B {
+///     C(C),
+/// }
+///
+/// enum A {
+///     B(B),
+/// }
+///
+/// from_chain!(A > B > C > D as DVariant);
+///
+/// let a: A = D.into();
+/// let a: A = C::from(D).into();
+/// ```
+///
+/// Like `from!`, chains can be made generic with an `impl<...>` prefix and an optional trailing
+/// `where` clause, which are used for every generated `From` impl.
+/// ```
+/// use treeerror::from_chain;
+///
+/// struct Leaf<T>(T);
+///
+/// enum Branch<T> {
+///     Leaf(Leaf<T>),
+/// }
+///
+/// enum Root<T> {
+///     Branch(Branch<T>),
+/// }
+///
+/// from_chain!(impl<T> Root<T> > Branch<T> as Branch > Leaf<T> as Leaf where T: Clone);
+///
+/// let root: Root<u8> = Leaf(0).into();
+/// ```
+///
+/// A type that shows up twice in the same chain would be converted into everything above it twice,
+/// so that's rejected with an error naming both links, rather than only the conflicting impls.
 #[macro_export]
 macro_rules! from_chain {
-    ($to:ty : $via:ident, $from:ty $(: $converter:ident, $next:ty)+) => (
-        $crate::from_many!($to : $via, $from, $($next),+);
-        $crate::from_chain!($from $(: $converter, $next)+);
+    // Generic chains get the generics and `where` clause split off first, which then get passed
+    // along to every `From` impl.
+    (impl < $($rest:tt)+) => (
+        $crate::split_generics! {
+            @callback [$crate::from_chain] [@generic] [] [] $($rest)+
+        }
     );
-    ($to:ty : $via:ident, $from:ty) => (
-        $crate::from!($to = $via($from));
+    (@generic @generics [$($g:tt)*] $($rest:tt)+) => (
+        $crate::split_where! {
+            @callback [$crate::from_chain] [@generics [$($g)*]] [] $($rest)+
+        }
+    );
+
+    // The whole chain gets checked for types showing up twice once, up front, so that the error
+    // names the links involved instead of just pointing at two conflicting impls. The impls are
+    // only generated once the check has passed.
+    ($(@generics $g:tt $w:tt)? $to:ty : $via:ident, $from:ty $(: $converter:ident, $next:ty)*) => (
+        $crate::unique_sources! {
+            @callback [$crate::from_chain]
+            [@chain $(@generics $g $w)? $to : $via, $from $(: $converter, $next)*]
+            [$to] [$from] $([$next])*
+        }
+    );
+    (@chain $(@generics $g:tt $w:tt)? $to:ty : $via:ident, $from:ty $(: $converter:ident, $next:ty)+) => (
+        $crate::from_many!($(@generics $g $w)? $to : $via, $from, $($next),+);
+        $crate::from_chain!(@chain $(@generics $g $w)? $from $(: $converter, $next)+);
+    );
+    (@chain $(@generics $g:tt $w:tt)? $to:ty : $via:ident, $from:ty) => (
+        $crate::from!($(@generics $g $w)? $to = $via($from));
+    );
+
+    // Shorthand where every variant is named after the type it wraps. This gets rewritten into
+    // the form above, one link at a time.
+    ($(@generics $g:tt $w:tt)? $to:ty > $($rest:tt)+) => (
+        $crate::from_chain!(@shorthand [$(@generics $g $w)?] [] ($to) > $($rest)+);
+    );
+    (@shorthand $generics:tt [$($done:tt)*] ($prev:ty) > $next:ty as $via:ident $(> $($rest:tt)+)?) => (
+        $crate::from_chain!(@shorthand $generics [$($done)* $prev : $via,] ($next) $(> $($rest)+)?);
+    );
+    (@shorthand $generics:tt [$($done:tt)*] ($prev:ty) > $next:ident $(> $($rest:tt)+)?) => (
+        $crate::from_chain!(@shorthand $generics [$($done)* $prev : $next,] ($next) $(> $($rest)+)?);
+    );
+    (@shorthand $generics:tt [$($done:tt)*] ($prev:ty) > $($rest:tt)+) => (
+        compile_error!("only types that are a single identifier can be used without `as Variant`");
+    );
+    (@shorthand [$($generics:tt)*] [$($done:tt)*] ($last:ty)) => (
+        $crate::from_chain!($($generics)* $($done)* $last);
+    );
+}
+
+/// Like `from_chain!`, but takes a whole hierarchy instead of a single path through it.
+///
+/// Every node is written as `Type { Variant: ChildType, ... }`, where children can have children
+/// of their own. This implements `From` for every type onto all of the types above it. Unlike
+/// `treeerror!`, the types need to already exist.
+/// ```
+/// use treeerror::from_tree;
+///
+/// struct One;
+/// struct Two;
+///
+/// enum Alpha {
+///     One(One),
+///     Two(Two),
+/// }
+///
+/// enum A {
+///     Alpha(Alpha),
+/// }
+///
+/// struct B;
+///
+/// enum Root {
+///     A(A),
+///     BVariant(B),
+/// }
+///
+/// from_tree!(Root {
+///     A: A {
+///         Alpha: Alpha {
+///             One: One,
+///             Two: Two,
+///         },
+///     },
+///     BVariant: B,
+/// });
+///
+/// let root: Root = One.into();
+/// let root: Root = Two.into();
+/// let root: Root = B.into();
+/// let a: A = Two.into();
+/// ```
+#[macro_export]
+macro_rules! from_tree {
+    ($root:ty { $($children:tt)* }) => (
+        $crate::from_tree!(@froms [] $root { $($children)* });
+    );
+    // Ancestors get passed around as a single token tree so they can be used for every child.
+    (@froms $ancestors:tt $node:ty {
+        $($via:ident : $child:ty $({ $($subtree:tt)* })?),* $(,)?
+    }) => (
+        $(
+            $crate::from!($node = $via($child));
+            $crate::from_tree!(@ancestors $ancestors $child > $node);
+            $($crate::from_tree!(@subtree $ancestors $node, $child { $($subtree)* });)?
+        )*
+    );
+    (@ancestors [$($ancestor:ty),*] $child:ty > $node:ty) => (
+        $($crate::from!($ancestor = $child > $node);)*
+    );
+    (@subtree [$($ancestor:ty),*] $node:ty, $child:ty { $($subtree:tt)* }) => (
+        $crate::from_tree!(@froms [$($ancestor,)* $node] $child { $($subtree)* });
     );
 }
 
@@ -159,20 +313,33 @@ macro_rules! from_chain {
 /// from!(A = |b: B| A(b));
 /// from_many!(A = D, C > B);
 /// ```
+///
+/// Both forms take the same `impl<...>` prefix and trailing `where` clause as `from!`.
 #[macro_export]
 macro_rules! from_many {
-    ($to:ty : $via:ident, $from:ty, $($continue:ty),+) => (
-        $crate::from!($to = $via($from));
-        $crate::from_many!($to : $via, $($continue),+);
+    (impl < $($rest:tt)+) => (
+        $crate::split_generics! {
+            @callback [$crate::from_many] [@generic] [] [] $($rest)+
+        }
+    );
+    (@generic @generics [$($g:tt)*] $($rest:tt)+) => (
+        $crate::split_where! {
+            @callback [$crate::from_many] [@generics [$($g)*]] [] $($rest)+
+        }
+    );
+
+    ($(@generics $g:tt $w:tt)? $to:ty : $via:ident, $from:ty, $($continue:ty),+) => (
+        $crate::from!($(@generics $g $w)? $to = $via($from));
+        $crate::from_many!($(@generics $g $w)? $to : $via, $($continue),+);
     );
-    ($to:ty : $via:ident, $from:ty $(,)?) => (
-        $crate::from!($to = $via($from));
+    ($(@generics $g:tt $w:tt)? $to:ty : $via:ident, $from:ty $(,)?) => (
+        $crate::from!($(@generics $g $w)? $to = $via($from));
     );
-    ($to:ty = $from:ty, $($continue:ty),+ > $via:ty) => (
-        $crate::from!($to = $from > $via);
-        $crate::from_many!($to = $($continue),+ > $via);
+    ($(@generics $g:tt $w:tt)? $to:ty = $from:ty, $($continue:ty),+ > $via:ty) => (
+        $crate::from!($(@generics $g $w)? $to = $from > $via);
+        $crate::from_many!($(@generics $g $w)? $to = $($continue),+ > $via);
     );
-    ($to:ty = $from:ty $(,)? > $via:ty) => (
-        $crate::from!($to = $from > $via);
+    ($(@generics $g:tt $w:tt)? $to:ty = $from:ty $(,)? > $via:ty) => (
+        $crate::from!($(@generics $g $w)? $to = $from > $via);
     );
 }
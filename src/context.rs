@@ -0,0 +1,207 @@
+use std::{borrow::Cow, error::Error, fmt};
+
+static EMPTY: ContextStack = ContextStack::new();
+
+/// Messages describing what was going on when an error happened, innermost first.
+///
+/// `treeerror!` roots marked with `@context` keep one of these in every leaf, which can be
+/// reached through `context()` on any enum in the tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContextStack(Vec<Cow<'static, str>>);
+
+impl ContextStack {
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// An empty stack, for errors that can't carry one.
+    pub fn empty() -> &'static Self {
+        &EMPTY
+    }
+
+    /// Adds a message on top of (outside of) the ones already there.
+    pub fn push(&mut self, context: impl Into<Cow<'static, str>>) {
+        self.0.push(context.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The messages, from the innermost (added first) to the outermost.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.0.iter().map(|context| context.as_ref())
+    }
+}
+
+impl Extend<Cow<'static, str>> for ContextStack {
+    fn extend<I: IntoIterator<Item = Cow<'static, str>>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+impl IntoIterator for ContextStack {
+    type Item = Cow<'static, str>;
+    type IntoIter = std::vec::IntoIter<Cow<'static, str>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// Renders the messages outermost first, separated by `: `.
+impl fmt::Display for ContextStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, context) in self.iter().rev().enumerate() {
+            if i != 0 {
+                f.write_str(": ")?;
+            }
+            f.write_str(context)?;
+        }
+        Ok(())
+    }
+}
+
+/// An error along with the context it happened in.
+///
+/// This is what [`ResultExt::context`] produces. Converting it into a `treeerror!` root marked
+/// with `@context` moves the context onto the root, and `into_context()` on the root moves it back
+/// out. `Display` shows the context, and the error itself is next in the `source()` chain. Without
+/// any context, both are passed through to the error instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Context<E> {
+    error: E,
+    context: ContextStack,
+}
+
+impl<E> Context<E> {
+    pub fn new(error: E, context: ContextStack) -> Self {
+        Self { error, context }
+    }
+
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    pub fn context(&self) -> &ContextStack {
+        &self.context
+    }
+
+    pub fn into_parts(self) -> (E, ContextStack) {
+        (self.error, self.context)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for Context<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.context.is_empty() {
+            self.error.fmt(f)
+        } else {
+            self.context.fmt(f)
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for Context<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        if self.context.is_empty() {
+            self.error.source()
+        } else {
+            Some(&self.error)
+        }
+    }
+}
+
+/// Adds context to the error of a `Result`, which is kept once it's converted into a
+/// `treeerror!` root marked with `@context`.
+/// ```
+/// use treeerror::{treeerror, ResultExt};
+///
+/// treeerror! {
+///     Root @context {
+///         Message(String),
+///     }
+/// }
+///
+/// fn read() -> Result<(), String> {
+///     Err("no such file".to_owned())
+/// }
+///
+/// fn load() -> Result<(), Root> {
+///     read().context("reading config.toml")?;
+///     Ok(())
+/// }
+///
+/// fn start(dir: &str) -> Result<(), Root> {
+///     load().with_context(|| format!("starting up in {dir}"))?;
+///     Ok(())
+/// }
+///
+/// let error = start("/srv").unwrap_err();
+/// assert_eq!(error.context().to_string(), "starting up in /srv: reading config.toml");
+/// ```
+pub trait ResultExt<T, E> {
+    fn context(self, context: impl Into<Cow<'static, str>>) -> Result<T, Context<E>>;
+
+    /// Like `context`, but only builds the message if there's an error.
+    fn with_context<C: Into<Cow<'static, str>>>(
+        self,
+        context: impl FnOnce() -> C,
+    ) -> Result<T, Context<E>>;
+}
+
+impl<T, E> ResultExt<T, E> for Result<T, E> {
+    fn context(self, context: impl Into<Cow<'static, str>>) -> Result<T, Context<E>> {
+        self.with_context(|| context)
+    }
+
+    fn with_context<C: Into<Cow<'static, str>>>(
+        self,
+        context: impl FnOnce() -> C,
+    ) -> Result<T, Context<E>> {
+        self.map_err(|error| {
+            let mut stack = ContextStack::new();
+            stack.push(context());
+            Context::new(error, stack)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Failure;
+
+    impl fmt::Display for Failure {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("failure")
+        }
+    }
+
+    impl Error for Failure {}
+
+    #[test]
+    fn test_render() {
+        let error = Err::<(), _>(Failure)
+            .context("inner")
+            .map_err(|context| {
+                let (error, mut stack) = context.into_parts();
+                stack.push("outer");
+                Context::new(error, stack)
+            })
+            .unwrap_err();
+        assert_eq!(error.to_string(), "outer: inner");
+        assert_eq!(error.source().unwrap().to_string(), "failure");
+        assert_eq!(error.context().iter().collect::<Vec<_>>(), ["inner", "outer"]);
+
+        let bare = Context::new(Failure, ContextStack::new());
+        assert_eq!(bare.to_string(), "failure");
+        assert!(bare.source().is_none());
+    }
+}
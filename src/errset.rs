@@ -0,0 +1,140 @@
+/// Declares an enum holding a subset of the variants of a `treeerror!` tree, for functions that
+/// can only fail in a few of the ways the whole tree describes.
+///
+/// Variants are named by their own names, or by the last few variants leading to them
+/// (`OtherWorld::W0`) where that's ambiguous, and can be leaves or whole subtrees. The set gets
+/// every variant as it is in the tree, along with `From` impls for everything converting into
+/// them, `From<Set> for Root`, and `TryFrom<Root> for Set` handing back the root for variants
+/// outside of the set. Variants behind a `#[cfg(...)]` can be part of a set when they're enabled
+/// where the tree is declared.
+///
+/// The root has to be named by the path it was declared at, which, like with `@mount`, needs
+/// `@export` on it if that's in another crate.
+/// ```
+/// use treeerror::{errset, treeerror};
+///
+/// treeerror! {
+///     #![derive(Debug)]
+///
+///     Hello {
+///         OtherWorld {
+///             W0 @unit,
+///             W1 @unit,
+///         },
+///         Terminal(String),
+///         Unknown @flatunit,
+///     }
+/// }
+///
+/// errset!(#[derive(Debug)] LoadError = Hello::{W0, W1, Terminal});
+///
+/// fn load() -> Result<(), LoadError> {
+///     Err(W1)?
+/// }
+///
+/// let error = load().unwrap_err();
+/// assert!(matches!(error, LoadError::W1(W1)));
+/// assert!(matches!(Hello::from(error), Hello::OtherWorld(OtherWorld::W1(W1))));
+/// assert!(matches!(LoadError::try_from(Hello::Unknown), Err(Hello::Unknown)));
+/// ```
+#[macro_export]
+macro_rules! errset {
+    ($($set:tt)*) => {
+        $crate::expand_errset! { $($set)* }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ResultExt;
+
+    crate::treeerror! {
+        #![derive(Debug)]
+
+        Tree @location @context @modules {
+            Config {
+                NotFound @unit,
+                Parse(std::num::ParseIntError),
+            },
+            Cache {
+                NotFound @unit,
+                Io @shared(std::io::Error),
+            },
+            Net @inline {
+                Timeout @unit,
+            },
+            Unknown @flatunit,
+            #[cfg(all())]
+            Refused @unit,
+            #[cfg(any())]
+            Gone @unit,
+        }
+    }
+
+    crate::errset!(#[derive(Debug)] Subset = Tree::{Config::NotFound, Io, Timeout, Unknown, Refused});
+    crate::errset!(#[derive(Debug)] Branch = self::Tree::{Config});
+
+    mod storage {
+        crate::treeerror! {
+            #![derive(Debug)]
+
+            Storage {
+                Disk {
+                    Full @unit,
+                },
+                Timeout @unit,
+            }
+        }
+    }
+
+    crate::treeerror! {
+        #![derive(Debug)]
+
+        App {
+            Storage @mount(storage::Storage),
+            Message(String),
+        }
+    }
+
+    crate::errset!(#[derive(Debug)] Mounted = App::{Storage});
+
+    #[test]
+    fn test_errset() {
+        use tree::{cache, config, net};
+
+        let subset = Subset::from(config::NotFound);
+        assert!(matches!(subset, Subset::NotFound(config::NotFound, _, _)));
+        let tree = Tree::from(Err::<(), _>(subset).context("loading").unwrap_err());
+        assert!(matches!(tree, Tree::Config(config::Config::NotFound(..))));
+        assert_eq!(tree.context().to_string(), "loading");
+        let line = tree.location().unwrap().line();
+
+        let subset = Subset::try_from(tree).unwrap();
+        let Subset::NotFound(_, location, context) = &subset else {
+            panic!("expected `NotFound`, got {subset:?}");
+        };
+        assert_eq!((location.line(), context.to_string().as_str()), (line, "loading"));
+
+        let io = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert!(matches!(Subset::from(io), Subset::Io(..)));
+        assert!(matches!(Subset::from(net::Timeout), Subset::Timeout(..)));
+        assert!(matches!(Subset::try_from(Tree::Unknown), Ok(Subset::Unknown)));
+        assert!(matches!(Subset::from(tree::Refused), Subset::Refused(..)));
+        assert!(matches!(Subset::try_from(Tree::from(cache::NotFound)), Err(Tree::Cache(_))));
+
+        let branch = Branch::from(config::NotFound);
+        assert!(matches!(Tree::from(branch), Tree::Config(config::Config::NotFound(..))));
+        let parse = "x".parse::<u8>().unwrap_err();
+        assert!(matches!(Branch::from(parse), Branch::Config(config::Config::Parse(..))));
+        assert!(Branch::try_from(Tree::from(cache::NotFound)).is_err());
+    }
+
+    #[test]
+    fn test_mounted() {
+        let full = Mounted::from(storage::Full);
+        assert!(matches!(full, Mounted::Storage(storage::Storage::Disk(storage::Disk::Full(_)))));
+        assert!(matches!(Mounted::from(storage::Disk::Full(storage::Full)), Mounted::Storage(_)));
+        assert!(matches!(Mounted::from(storage::Timeout), Mounted::Storage(storage::Storage::Timeout(_))));
+        assert!(Mounted::try_from(App::from("hi".to_owned())).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 #![feature(more_qualified_paths)]
+#![allow(unused_features)]
 
 //! This crate is intended to provide some convenience enums for mapping enums from one type to
 //! another. This is intended as an expansion of `thiserror` that has more compile time mapping
@@ -49,8 +50,8 @@
 //! );
 //! ```
 //!
-//! You'll notice that there are many `from_many` call here -- automatically generating this
-//! tree is not yet complete and in progress.
+//! You'll notice that there are many `from_many` calls here. [`treeerror!`] declares the whole
+//! tree at once instead, generating the enums along with every `From` impl between them.
 //!
 //! We can also handle "flatter" error structures where, instead of nesting these enums by
 //! wrapping them, we destructure the internal values instead.
@@ -90,10 +91,9 @@
 //! );
 //! ```
 //!
-//! Some things that remain: combining all of this in a nice interface to autogenerate the
-//! error chains, as well as packaging all of this stuff in a way to enable better error
-//! messages during compilation. This potentially includes a proc macro rewrite to enable
-//! better error messages and configurability.
+//! Trees declared with [`treeerror!`] can be carved into smaller sets of errors with
+//! [`errset!`] and matched deep down with [`tree_pat!`]. The derives, [`MapEnum`] and
+//! [`TreeError`], generate the same impls from attributes on existing enums.
 
 mod shared;
 
@@ -101,6 +101,131 @@ mod simple;
 mod chain;
 mod mapping;
 mod tree;
+mod context;
+mod errset;
+mod tree_pat;
+
+pub use context::{Context, ContextStack, ResultExt};
+
+// Lets the derives refer to `::treeerror` from within this crate as well.
+extern crate self as treeerror;
+
+/// Attribute-driven alternative to `map_enum!`, derived on the enum being mapped onto.
+///
+/// Every `#[map_from(Source)]` on the enum generates a `From<Source>` impl. By default, each
+/// variant is mapped from the source variant with the same name, with unit variants mapped as
+/// `@unit` and variants with several fields mapping every field in order. Variants can be
+/// customized with `#[map(...)]`:
+///
+/// - `from = Variant` maps from a differently named source variant (`Variant > Target`).
+/// - `conv` converts the payload with `.into()` (`@conv`).
+/// - `unit` maps the variant as a unit variant (`@unit`).
+/// - `source = Source` only applies the mapping to one of the sources. A variant can have one
+///   `#[map(...)]` per source.
+/// - `skip` leaves the variant out of the mapping entirely.
+///
+/// `#[map_from(...)]` itself also takes `flatten(Variant, ...)` for source variants whose payload
+/// converts into the whole target enum (`@flatten @conv`), and `catch = |e| ...` for a catch-all.
+///
+/// ```
+/// #![feature(more_qualified_paths)]
+/// mod impls {
+///     use treeerror::MapEnum;
+///
+///     #[derive(Debug)]
+///     pub struct WebError;
+///
+///     pub mod suberror0 {
+///         #[derive(Debug)]
+///         pub struct MemoryError;
+///         #[derive(Debug)]
+///         pub enum E {
+///             NotFound,
+///             Web(super::WebError),
+///             Memory(MemoryError),
+///         }
+///     }
+///
+///     pub mod suberror1 {
+///         #[derive(Debug)]
+///         pub struct MemoryError;
+///         #[derive(Debug)]
+///         pub enum E {
+///             Web(super::WebError),
+///             Memory(MemoryError),
+///             WeirdInternalErrorThatShouldNotBeSurfaced,
+///         }
+///     }
+///
+///     #[derive(MapEnum)]
+///     #[map_from(suberror0::E)]
+///     #[map_from(suberror1::E, catch = |e| panic!("this should not happen... {e:?}"))]
+///     pub enum SharedError {
+///         #[map(source = suberror0::E)]
+///         NotFound,
+///         Web(WebError),
+///         #[map(source = suberror0::E, from = Memory)]
+///         Memory0(suberror0::MemoryError),
+///         #[map(source = suberror1::E, from = Memory)]
+///         Memory1(suberror1::MemoryError),
+///     }
+/// }
+///
+/// use impls::{suberror1, SharedError};
+///
+/// let e: SharedError = suberror1::E::Memory(suberror1::MemoryError).into();
+/// assert!(matches!(e, SharedError::Memory1(_)));
+/// ```
+pub use treeerror_derive::MapEnum;
+
+/// Builds the same `From` impls as `from_chain!`, but from the enum definitions themselves.
+///
+/// Every variant marked with `#[tree]` gets a `From` impl for the type it wraps. If that type
+/// derives `TreeError` as well, everything below it also gets converted via the wrapped type, so
+/// that the lowest types can be converted all the way up to the root. Types that don't derive
+/// `TreeError` (third party errors, for example) can be wrapped by marking the variant with
+/// `#[tree(leaf)]` instead. Leaves that are structs can also derive `TreeError` to avoid this.
+///
+/// The derives communicate through macros expanded at the parent's location, so the types in
+/// the hierarchy must be reachable with the same paths from every module that has a parent in
+/// it (by keeping them in one module, or by using `crate::` paths, for example).
+///
+/// ```
+/// use treeerror::TreeError;
+///
+/// #[derive(TreeError)]
+/// enum Root {
+///     #[tree]
+///     A(A),
+///     #[tree(leaf)]
+///     Fmt(std::fmt::Error),
+/// }
+/// #[derive(TreeError)]
+/// enum A {
+///     #[tree]
+///     Alpha(Alpha),
+/// }
+/// #[derive(TreeError)]
+/// enum Alpha {
+///     #[tree]
+///     One(One),
+/// }
+/// #[derive(TreeError)]
+/// enum One {
+///     #[tree]
+///     Child(Child),
+/// }
+/// #[derive(TreeError)]
+/// struct Child;
+///
+/// let _: Root = Child.into();
+/// let _: Root = One::from(Child).into();
+/// let _: A = Child.into();
+/// let _: Root = std::fmt::Error.into();
+/// ```
+pub use treeerror_derive::TreeError;
+#[doc(hidden)]
+pub use treeerror_derive::{expand_errset, expand_tree, expand_tree_pat, shared_mappings, unique_sources};
 
 use std::future::Future;
 
@@ -134,6 +259,7 @@ mod test {
         from!(One = Child(Child));
 
         #[test]
+        #[allow(clippy::useless_conversion)]
         fn test_from_impls() {
             let _: One = Child.into();
             let _: Alpha = One::from(Child).into();
@@ -166,6 +292,7 @@ mod test {
         from_chain!(Root : A, A : Alpha, Alpha : One, One : Child, Child);
 
         #[test]
+        #[allow(clippy::useless_conversion)]
         fn test_from_impls() {
             let _: One = Child.into();
             let _: Alpha = One::from(Child).into();
@@ -178,5 +305,191 @@ mod test {
         }
     }
 
+    mod generic {
+        use crate::*;
+        use std::fmt::Debug;
+
+        #[allow(dead_code)]
+        enum Root<T> {
+            A(A<T>),
+        }
+        #[allow(dead_code)]
+        enum A<T> {
+            Alpha(Alpha<T>),
+        }
+        enum Alpha<T> {
+            One(One<T>),
+        }
+        struct One<T>(T);
+
+        from_chain!(impl<T> Root<T> : A, A<T> : Alpha, Alpha<T> : One, One<T> where T: Debug);
+
+        #[allow(dead_code)]
+        enum Other<'a, T> {
+            Alpha(Alpha<T>),
+            Named(&'a str),
+        }
+        from_many!(impl<'a, T: Into<Vec<u8>> + Debug> Other<'a, T> : Alpha, Alpha<T>, One<T>);
+        from!(impl<'a, T> Other<'a, T> = |s: &'a str| Other::Named(s));
+
+        #[test]
+        fn test_from_impls() {
+            let _: Alpha<u8> = One(0).into();
+            let _: A<u8> = One(0).into();
+            let _: Root<u8> = One(0).into();
+            let _: Root<u8> = Alpha::from(One(0)).into();
+            let _: Other<String> = One(String::new()).into();
+            let _: Other<String> = "named".into();
+        }
+    }
+
+    mod shorthand {
+        use crate::*;
+
+        enum Root {
+            A(A),
+        }
+        enum A {
+            AlphaVariant(Alpha),
+        }
+        enum Alpha {
+            One(One),
+        }
+        enum One {
+            Child(inner::Child),
+        }
+        mod inner {
+            pub struct Child;
+        }
+        use inner::Child;
+
+        from_chain!(Root > A > Alpha as AlphaVariant > One > inner::Child as Child);
+
+        #[test]
+        fn test_from_impls() {
+            let _: One = Child.into();
+            let _: Alpha = One::from(Child).into();
+            let _: A = Alpha::from(Child).into();
+            let _: Root = Root::from(Child);
+
+            let _: Alpha = Child.into();
+            let _: A = Child.into();
+            let _: Root = Child.into();
+        }
+    }
+
+    mod tree_shaped {
+        use crate::*;
+
+        #[allow(dead_code)]
+        enum Root {
+            A(A),
+            B(B),
+        }
+        enum A {
+            Alpha(Alpha),
+            Beta(Beta),
+        }
+        enum Alpha {
+            One(One),
+        }
+        enum One {
+            Child(Child),
+        }
+        enum B {
+            Other(Other),
+        }
+        struct Child;
+        struct Beta;
+        struct Other;
+
+        from_tree!(Root {
+            A: A {
+                Alpha: Alpha {
+                    One: One {
+                        Child: Child,
+                    },
+                },
+                Beta: Beta,
+            },
+            B: B { Other: Other },
+        });
+
+        #[test]
+        fn test_from_impls() {
+            let _: One = Child.into();
+            let _: Alpha = One::from(Child).into();
+            let _: A = Alpha::from(Child).into();
+            let _: Root = Root::from(Child);
+
+            let _: Alpha = Child.into();
+            let _: A = Child.into();
+            let _: Root = Child.into();
+            let _: Root = Beta.into();
+            let _: Root = Other.into();
+        }
+    }
+
+    mod derived {
+        use crate::TreeError;
+
+        #[allow(dead_code)]
+        #[derive(TreeError)]
+        enum Root {
+            #[tree]
+            A(A),
+            #[tree]
+            B(inner::B),
+        }
+        #[allow(dead_code)]
+        #[derive(TreeError)]
+        enum A {
+            #[tree]
+            Alpha(Alpha),
+            #[tree(leaf)]
+            Leaf(String),
+        }
+        #[derive(TreeError)]
+        enum Alpha {
+            #[tree]
+            One(One),
+        }
+        #[derive(TreeError)]
+        enum One {
+            #[tree]
+            Child(Child),
+        }
+        #[derive(TreeError)]
+        struct Child;
+
+        mod inner {
+            use crate::TreeError;
+
+            #[derive(TreeError)]
+            pub enum B {
+                #[tree(leaf)]
+                Beta(crate::test::derived::Beta),
+                #[allow(dead_code)]
+                Untracked(u8),
+            }
+        }
+        pub struct Beta;
+
+        #[test]
+        fn test_from_impls() {
+            let _: One = Child.into();
+            let _: Alpha = One::from(Child).into();
+            let _: A = Alpha::from(Child).into();
+            let _: Root = Root::from(Child);
+
+            let _: Alpha = Child.into();
+            let _: A = Child.into();
+            let _: Root = Child.into();
+            let _: Root = One::from(Child).into();
+            let _: Root = String::new().into();
+            let _: Root = Beta.into();
+        }
+    }
+
     // from_chain!(Child, One:Child, Alpha:One, A:Alpha, Root:A);
 }
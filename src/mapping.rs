@@ -124,6 +124,12 @@
 /// mapped onto the same error (for example, three crates that depend on `reqwest` which
 /// then individually wrap `reqwest`'s error in their own error). This can also be combined
 /// with `from_chain!` for more functionality.
+///
+/// Variants wrapping other enums can be matched more than one level deep by writing out the
+/// nested pattern, in which case the fields of the target variant have to be given as
+/// expressions (`Memory(WrappedMemoryError::SomeError(e)) > Memory1(e)`). The generated `match`
+/// is still checked for exhaustiveness, so a new variant in the nested enum becomes a compile
+/// error.
 /// ```
 /// #![feature(more_qualified_paths)]
 /// mod impls {
@@ -173,12 +179,7 @@
 ///
 ///     map_enum!(suberror1::E > SharedError {
 ///         Web,
-///         Memory = (a) {
-///             let suberror1::WrappedMemoryError::SomeError(e) = a else {
-///                 unreachable!("only one variant exists");
-///             };
-///             SharedError::Memory1(e)
-///         },
+///         Memory(suberror1::WrappedMemoryError::SomeError(e)) > Memory1(e),
 ///     } |e| {
 ///         panic!("this should not happen... {e:?}")
 ///     });
@@ -191,28 +192,252 @@
 /// let m: impls::SharedError = impls::suberror1::MemoryError.into();
 ///
 /// ```
+///
+/// The two mappings above can also be written as a single invocation. Every source listed gets
+/// its own `From` impl using the shared mappings, and a source can be followed by a block of
+/// overrides (and its own catch-all). An override replaces the shared mapping for its variant,
+/// which then doesn't need to type check for that source, unless it only matches part of the
+/// variant through a nested pattern or a guard, in which case it's tried first. Sources without
+/// their own catch-all use the shared one, if any.
+/// ```
+/// #![feature(more_qualified_paths)]
+/// # mod impls {
+/// #     use treeerror::map_enum;
+/// #
+/// #     #[derive(Debug)]
+/// #     pub struct WebError;
+/// #
+/// #     pub mod suberror0 {
+/// #         #[derive(Debug)]
+/// #         pub struct MemoryError;
+/// #         #[derive(Debug)]
+/// #         pub enum E {
+/// #             NotFound,
+/// #             Web(super::WebError),
+/// #             Memory(MemoryError),
+/// #         }
+/// #     }
+/// #
+/// #     pub mod suberror1 {
+/// #         #[derive(Debug)]
+/// #         pub struct MemoryError;
+/// #         #[derive(Debug)]
+/// #         pub enum WrappedMemoryError {
+/// #             SomeError(MemoryError),
+/// #         }
+/// #         #[derive(Debug)]
+/// #         pub enum E {
+/// #             Web(super::WebError),
+/// #             Memory(WrappedMemoryError),
+/// #             WeirdInternalErrorThatShouldNotBeSurfaced,
+/// #         }
+/// #     }
+/// #
+/// #     pub enum SharedError {
+/// #         NotFound,
+/// #         Web(WebError),
+/// #         Memory0(suberror0::MemoryError),
+/// #         Memory1(suberror1::MemoryError),
+/// #     }
+/// #
+///     map_enum!(
+///         suberror0::E {
+///             @unit NotFound,
+///             Memory > Memory0,
+///         },
+///         suberror1::E {
+///             Memory(suberror1::WrappedMemoryError::SomeError(e)) > Memory1(e),
+///         } |e| {
+///             panic!("this should not happen... {e:?}")
+///         }
+///         > SharedError {
+///             Web,
+///         }
+///     );
+/// # }
+///
+/// let m: impls::SharedError = impls::suberror0::E::Web(impls::WebError).into();
+/// ```
+///
+/// The target doesn't have to be an enum. Mappings written as `Variant(patterns) => value` match
+/// the patterns (and an optional `if` guard) against the variant's fields and produce the value
+/// as is, so any type (status codes, exit codes, log levels, ...) can be mapped onto. These can
+/// be mixed with the other forms when the target is an enum.
+/// ```
+/// #![feature(more_qualified_paths)]
+/// use treeerror::map_enum;
+///
+/// enum StoreError {
+///     NotFound,
+///     Io(std::io::Error),
+///     Corrupt(u64),
+/// }
+///
+/// map_enum!(StoreError > u16 {
+///     NotFound => 404,
+///     Io(_) => 500,
+///     Corrupt(0) => 400,
+///     Corrupt(offset) if offset < 512 => 422,
+///     Corrupt(_) => 500,
+/// });
+///
+/// assert_eq!(u16::from(StoreError::NotFound), 404);
+/// assert_eq!(u16::from(StoreError::Corrupt(12)), 422);
+/// ```
+///
+/// Errors that are only borrowed (for logging or metrics, for example) can be classified by
+/// mapping from a reference instead. This generates `From<&Source>`, cloning payloads into the
+/// target. Payloads mapped through a block are handed to the block as references instead.
+/// ```
+/// #![feature(more_qualified_paths)]
+/// use treeerror::map_enum;
+///
+/// enum StoreError {
+///     NotFound(String),
+///     Io(std::io::Error),
+///     Timeout,
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Metric {
+///     NotFound(String),
+///     Io,
+///     Timeout,
+/// }
+///
+/// map_enum!(&StoreError > Metric {
+///     NotFound,
+///     Io = (_e) { Metric::Io },
+///     @unit Timeout,
+/// });
+///
+/// let e = StoreError::NotFound("key".to_owned());
+/// assert_eq!(Metric::from(&e), Metric::NotFound("key".to_owned()));
+/// ```
+///
+/// Generic enums can be mapped by prefixing the mapping with `impl<...>`. The parameters (and an
+/// optional `where` clause after the target) are carried over onto the generated `impl`.
+/// ```
+/// #![feature(more_qualified_paths)]
+/// use treeerror::map_enum;
+///
+/// enum StorageError<E> {
+///     Backend(E),
+///     Missing,
+/// }
+///
+/// enum AppError<E> {
+///     Storage(E),
+///     NotFound,
+/// }
+///
+/// map_enum!(impl<E> StorageError<E> > AppError<E> where E: std::error::Error {
+///     Backend > Storage,
+///     @unit Missing > NotFound,
+/// });
+///
+/// let a: AppError<std::fmt::Error> = StorageError::Backend(std::fmt::Error).into();
+/// ```
 #[macro_export]
 macro_rules! map_enum {
+    // Generic mappings. The parameters get split off by `split_generics!`, after which the
+    // `where` clause (if any) is munched up until the body of the mapping.
+    (impl < $($rest:tt)+) => {
+        $crate::split_generics! {
+            @callback [$crate::map_enum] [@generic] [] [] $($rest)+
+        }
+    };
+    (@generic @generics [$($g:tt)*] $from:path > $to:ty where $($rest:tt)+) => {
+        $crate::map_enum! { @where [$($g)*] [] ($from > $to) $($rest)+ }
+    };
+    (@generic @generics [$($g:tt)*] $from:path > $to:ty { $($body:tt)* } $($catch:tt)*) => {
+        $crate::map_enum! { @generics [$($g)*] [] $from > $to { $($body)* } $($catch)* }
+    };
+    (@where [$($g:tt)*] [$($w:tt)*] ($from:path > $to:ty) { $($body:tt)* } $($catch:tt)*) => {
+        $crate::map_enum! { @generics [$($g)*] [$($w)*] $from > $to { $($body)* } $($catch)* }
+    };
+    (@where [$($g:tt)*] [$($w:tt)*] ($($header:tt)+) $next:tt $($rest:tt)*) => {
+        $crate::map_enum! { @where [$($g)*] [$($w)* $next] ($($header)+) $($rest)* }
+    };
+
     // TODO Add support for specifying "dropping out" of some identities.
-    ($from:path > $to:path {
-        $($(@$m:ident)* $match:ident $(> $wrap:ident)? $(= ($($p:ident),*))? $($blk:block)?),+ $(,)?
+    ($(@generics [$($g:tt)*] [$($w:tt)*])? $from:path > $to:ty {
+        $(
+            $(@$m:ident)* $match:ident $(($($args:tt)*))? $(> $wrap:ident $(($($wargs:expr),*))?)?
+            $(= ($($p:ident),*))? $($blk:block)? $($(if $guard:expr)? => $value:expr)?
+        ),+ $(,)?
     } $($(|$e:ident|)? $catch:block)?) => {
-        impl From<$from> for $to {
+        impl$(<$($g)*>)? From<$from> for $to $(where $($w)*)? {
             fn from(e: $from) -> Self {
                 match e {
                     $($crate::map_enum!(@coerce pat $crate::map_enum!(
-                        @invocation pat
+                        @arm pat
+                        (<$from>::$match)
+                        __some_tok
+                        $(@$m)*
+                        ($($($p),*)?)
+                        [$(($($args)*))?]
+                        [$($value)?]
+                    )) $($(if $guard)?)? => {
+                        $crate::map_enum!(
+                            @arm expr
+                            ($crate::map_enum!(@unwrap_opt $($wrap)? $match (<$to>::)))
+                            __some_tok
+                            $(@$m)*
+                            ($($($p),*)?)
+                            [$(($($args)*))?]
+                            [$($(($($wargs),*))?)?]
+                            [$($value)?]
+                            $($blk)?
+                        )
+                    })+
+                    $(e => {
+                        $(let $e = e;)?
+                        $catch
+                    })?
+                }
+            }
+        }
+    };
+
+    // Mappings from a borrowed source. Payloads get cloned into the target unless a block is
+    // provided, in which case the block gets the references as is.
+    (&$from:path > $to:ty {
+        $(
+            $(@$m:ident)* $match:ident $(($($args:tt)*))? $(> $wrap:ident $(($($wargs:expr),*))?)?
+            $(= ($($p:ident),*))? $($blk:block)? $($(if $guard:expr)? => $value:expr)?
+        ),+ $(,)?
+    } $($(|$e:ident|)? $catch:block)?) => {
+        impl From<&$from> for $to {
+            fn from(e: &$from) -> Self {
+                match e {
+                    $($crate::map_enum!(@coerce pat $crate::map_enum!(
+                        @arm pat
                         (<$from>::$match)
                         __some_tok
                         $(@$m)*
                         ($($($p),*)?)
-                    )) => {
+                        [$(($($args)*))?]
+                        [$($value)?]
+                    )) $($(if $guard)?)? => {
+                        $crate::map_enum!(
+                            @clone_refs
+                            __some_tok
+                            $(@$m)*
+                            ($($($p),*)?)
+                            [$(($($args)*))?]
+                            [$($value)?]
+                            $($blk)?
+                        );
                         $crate::map_enum!(
-                            @invocation expr
+                            @arm expr
                             ($crate::map_enum!(@unwrap_opt $($wrap)? $match (<$to>::)))
                             __some_tok
                             $(@$m)*
                             ($($($p),*)?)
+                            [$(($($args)*))?]
+                            [$($(($($wargs),*))?)?]
+                            [$($value)?]
                             $($blk)?
                         )
                     })+
@@ -225,6 +450,96 @@ macro_rules! map_enum {
         }
     };
 
+    // Many-to-one mappings. Every source gets its own `From` impl made up of its overrides
+    // followed by the shared mappings. Shared mappings for a variant that an override maps
+    // completely are left out, while overrides with a nested pattern or a guard are matched
+    // before the shared mapping for the rest of the variant.
+    ($($from:path $({ $($over:tt)* } $($(|$e:ident|)? $catch:block)?)?),+ > $to:ty {
+        $($shared:tt)*
+    } $($(|$shared_e:ident|)? $shared_catch:block)?) => {
+        $crate::map_enum! {
+            @many $to
+            [$($shared)*]
+            [$($(|$shared_e|)? $shared_catch)?]
+            $([$from $({ $($over)* } $($(|$e|)? $catch)?)?])+
+        }
+    };
+    (@many $to:ty [$($shared:tt)*] [$($shared_catch:tt)*]) => {};
+    (@many $to:ty [$($shared:tt)*] [$($shared_catch:tt)*]
+        [$from:path $({ $($over:tt)* } $($catch:tt)*)?]
+        $($rest:tt)*
+    ) => {
+        $crate::map_enum! {
+            @many_one $from > $to
+            [$($($over)*)?]
+            [$($shared)*]
+            [$($($catch)*)?]
+            [$($shared_catch)*]
+        }
+        $crate::map_enum! { @many $to [$($shared)*] [$($shared_catch)*] $($rest)* }
+    };
+    // Sources without their own catch-all fall back onto the shared one.
+    (@many_one $from:path > $to:ty [$($over:tt)*] [$($shared:tt)*] [] [$($shared_catch:tt)+]) => {
+        $crate::map_enum! { @many_one $from > $to [$($over)*] [$($shared)*] [$($shared_catch)+] [] }
+    };
+    (@many_one $from:path > $to:ty
+        [$(
+            $(@$om:ident)* $omatch:ident $(($($oargs:tt)*))? $(> $owrap:ident $(($($owargs:expr),*))?)?
+            $(= ($($op:ident),*))? $($oblk:block)? $($(if $oguard:expr)? => $ovalue:expr)?
+        ),* $(,)?]
+        [$(
+            $(@$sm:ident)* $smatch:ident $(($($sargs:tt)*))? $(> $swrap:ident $(($($swargs:expr),*))?)?
+            $(= ($($sp:ident),*))? $($sblk:block)? $($(if $sguard:expr)? => $svalue:expr)?
+        ),* $(,)?]
+        [$($catch:tt)*]
+        [$($ignored:tt)*]
+    ) => {
+        $crate::shared_mappings! {
+            @callback [$crate::map_enum] [@many_kept ($from > $to) [$($catch)*] [$(
+                $(@$om)* $omatch $(($($oargs)*))? $(> $owrap $(($($owargs),*))?)?
+                $(= ($($op),*))? $($oblk)? $($(if $oguard)? => $ovalue)?,
+            )*]]
+            [$([$omatch $(($($oargs)*))? $($(if $oguard)?)?])*]
+            $([$smatch] [
+                $(@$sm)* $smatch $(($($sargs)*))? $(> $swrap $(($($swargs),*))?)?
+                $(= ($($sp),*))? $($sblk)? $($(if $sguard)? => $svalue)?,
+            ])*
+        }
+    };
+    (@many_kept ($from:path > $to:ty) [$($catch:tt)*] [$($over:tt)*] [$($shared:tt)*]) => {
+        $crate::map_enum! { $from > $to { $($over)* $($shared)* } $($catch)* }
+    };
+
+    // Nested patterns (`Variant(pattern) > Target(exprs)`) and mappings given as
+    // `Variant(pattern) => value` are used as is, everything else goes through the regular
+    // invocation rules below.
+    (@arm pat ($($path:tt)+) $escaped:ident $(@$m:ident)* ($($tail:tt)*) [($($args:tt)*)] [$($value:tt)*]) => (
+        $($path)+ ($($args)*)
+    );
+    (@arm pat ($($path:tt)+) $escaped:ident $(@$m:ident)* ($($tail:tt)*) [] [$($value:tt)+]) => (
+        $($path)+
+    );
+    (@arm pat ($($path:tt)+) $escaped:ident $(@$m:ident)* ($($tail:tt)*) [] []) => (
+        $crate::map_enum!(@invocation pat ($($path)+) $escaped $(@$m)* ($($tail)*))
+    );
+    (@arm expr ($($path:tt)+) $escaped:ident $(@$m:ident)* ($($tail:tt)*) [$($args:tt)*] [$($wargs:tt)*] [$($value:tt)+]) => (
+        $($value)+
+    );
+    (@arm expr ($($path:tt)+) $escaped:ident @conv ($($tail:tt)*) [$($args:tt)*] [($($wargs:expr),*)] []) => (
+        $($path)+ ($($wargs.into()),*)
+    );
+    (@arm expr ($($path:tt)+) $escaped:ident $(@$m:ident)* ($($tail:tt)*) [$($args:tt)*] [($($wargs:expr),*)] []) => (
+        $($path)+ ($($wargs),*)
+    );
+    // Nested patterns bind names of their own, so there's nothing to build the target out of
+    // without being told how.
+    (@arm expr ($($path:tt)+) $escaped:ident $(@$m:ident)* ($($tail:tt)*) [$($args:tt)+] [] [] $($blk:block)?) => (
+        compile_error!("mappings with a nested pattern need a `> Target(exprs)` building the target from its bindings")
+    );
+    (@arm expr ($($path:tt)+) $escaped:ident $(@$m:ident)* ($($tail:tt)*) [] [] [] $($blk:block)?) => (
+        $crate::map_enum!(@invocation expr ($($path)+) $escaped $(@$m)* ($($tail)*) $($blk)?)
+    );
+
     // This generates the pattern matching the original value that's being converted
     // from.
     (@invocation pat ($($path:tt)+) $escaped:ident @unit ($($tail:tt)*)) => (
@@ -271,6 +586,19 @@ macro_rules! map_enum {
         $($path)+ ($($params.into()),*)
     );
 
+    // Turns the references bound by a borrowed match into owned values.
+    // Patterns and values written out by the user are left alone.
+    (@clone_refs $escaped:ident $(@$m:ident)* ($($tail:tt)*) [$($args:tt)+] [$($value:tt)*] $($blk:block)?) => ();
+    (@clone_refs $escaped:ident $(@$m:ident)* ($($tail:tt)*) [] [$($value:tt)+] $($blk:block)?) => ();
+    (@clone_refs $escaped:ident @unit ($($tail:tt)*) [] [] $($blk:block)?) => ();
+    (@clone_refs $escaped:ident $(@$m:ident)* ($($tail:tt)*) [] [] $blk:block) => ();
+    (@clone_refs $escaped:ident $(@$m:ident)* () [] []) => (
+        let $escaped = ::core::clone::Clone::clone($escaped);
+    );
+    (@clone_refs $escaped:ident $(@$m:ident)* ($($params:ident),+) [] []) => (
+        $(let $params = ::core::clone::Clone::clone($params);)+
+    );
+
     // Needed to force the compiler to treat some things as specific kinds of tokens instead of
     // generic token trees.
     (@coerce pat $stuff:pat) => ($stuff);
@@ -404,6 +732,7 @@ mod test {
         });
     }
 
+    #[allow(clippy::enum_variant_names)]
     mod alltogether {
         test_types!(S, F);
         map_enum!(S > F {
@@ -415,4 +744,360 @@ mod test {
             @unit Unit,
         });
     }
+
+    mod values {
+        test_types!(Sub, Full);
+
+        map_enum!(Sub > u16 {
+            I(_) => 1,
+            S(s) => s.len() as u16,
+            M(_, u) => u as u16,
+        } |_ignored| {
+            0
+        });
+
+        map_enum!(Sub > &'static str {
+            I(_) => "number",
+            U(_) => "number",
+            S(_) => "text",
+            R(_) => "text",
+            M(..) => "pair",
+            Unit => "unit",
+        });
+
+        map_enum!(Sub > Full {
+            I,
+            S(s) => Full::Sb(s),
+            U > Ub,
+            R(r) if r.is_empty() => Full::Unit,
+            R > Rb,
+            M > Ma = (a, b),
+            @unit Unit,
+        });
+
+        #[test]
+        fn test_value_targets() {
+            assert_eq!(u16::from(Sub::I(3)), 1);
+            assert_eq!(u16::from(Sub::S("abc".to_owned())), 3);
+            assert_eq!(u16::from(Sub::M(0, 7)), 7);
+            assert_eq!(u16::from(Sub::Unit), 0);
+            assert_eq!(<&str>::from(Sub::U(0)), "number");
+            assert_eq!(<&str>::from(Sub::R("")), "text");
+        }
+
+        #[test]
+        fn test_mixed_forms() {
+            assert_eq!(Full::from(Sub::I(1)), Full::I(1));
+            assert_eq!(Full::from(Sub::S("s".to_owned())), Full::Sb("s".to_owned()));
+            assert_eq!(Full::from(Sub::R("")), Full::Unit);
+            assert_eq!(Full::from(Sub::R("r")), Full::Rb("r"));
+        }
+    }
+
+    mod derived {
+        use crate::{from, MapEnum};
+
+        #[allow(dead_code)]
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        enum Sub {
+            I(i32),
+            S(String),
+            U(u16),
+            M(i32, u64),
+            Unit,
+            Nested(Nested),
+        }
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        enum Nested {
+            Ib(i32),
+        }
+        #[allow(dead_code)]
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        enum Other {
+            I(i32),
+            Extra,
+        }
+
+        #[allow(dead_code)]
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, MapEnum)]
+        #[map_from(Sub, flatten(Nested))]
+        #[map_from(Other, catch = |_| Full::Unit)]
+        enum Full {
+            #[map(source = Sub)]
+            I(i32),
+            #[map(source = Sub)]
+            S(String),
+            #[map(source = Sub, from = U, conv)]
+            Ub(u64),
+            #[map(source = Sub, from = M)]
+            Ma(i32, u64),
+            #[map(source = Other, from = I)]
+            Ib(i32),
+            #[map(source = Sub)]
+            Unit,
+            #[map(skip)]
+            Skipped,
+        }
+        from!(Full = |n: Nested| match n {
+            Nested::Ib(i) => Full::Ib(i),
+        });
+
+        #[derive(Debug, PartialEq, Eq, MapEnum)]
+        #[map_from(Inner<E>)]
+        enum Outer<E> where E: Clone {
+            Storage(E),
+            #[map(from = Missing)]
+            NotFound,
+        }
+        enum Inner<E> {
+            Storage(E),
+            Missing,
+        }
+
+        #[test]
+        fn test_derived_from() {
+            assert_eq!(Full::from(Sub::I(1)), Full::I(1));
+            assert_eq!(Full::from(Sub::S("s".to_owned())), Full::S("s".to_owned()));
+            assert_eq!(Full::from(Sub::U(2)), Full::Ub(2));
+            assert_eq!(Full::from(Sub::M(1, 2)), Full::Ma(1, 2));
+            assert_eq!(Full::from(Sub::Unit), Full::Unit);
+            assert_eq!(Full::from(Sub::Nested(Nested::Ib(3))), Full::Ib(3));
+            assert_eq!(Full::from(Other::I(1)), Full::Ib(1));
+            assert_eq!(Full::from(Other::Extra), Full::Unit);
+        }
+
+        #[test]
+        fn test_derived_generic_from() {
+            assert_eq!(Outer::from(Inner::Storage(1u8)), Outer::Storage(1u8));
+            assert_eq!(Outer::<u8>::from(Inner::Missing), Outer::NotFound);
+        }
+    }
+
+    mod nested {
+        #[derive(Debug, PartialEq, Eq)]
+        enum Leaf {
+            A(i32),
+            B(String),
+        }
+        #[derive(Debug, PartialEq, Eq)]
+        enum Source {
+            Leaf(Leaf),
+            Pair(Leaf, u8),
+            Plain(u8),
+        }
+        #[derive(Debug, PartialEq, Eq)]
+        enum Target {
+            A(i64),
+            B(String),
+            Pair(String, u16),
+            Plain(u8),
+        }
+
+        map_enum!(Source > Target {
+            @conv Leaf(Leaf::A(a)) > A(a),
+            Leaf(Leaf::B(b)) > B(b),
+            Pair(Leaf::A(a), n) > Pair(a.to_string(), n as u16),
+            Pair(Leaf::B(b), n) > Pair(b, n.into()),
+            Plain,
+        });
+
+        map_enum!(&Source > Target {
+            Leaf(Leaf::A(a)) > A((*a).into()),
+            Leaf(Leaf::B(b)) > B(b.clone()),
+            Pair(_, n) > Pair(String::new(), (*n).into()),
+            Plain,
+        });
+
+        #[test]
+        fn test_nested_patterns() {
+            assert_eq!(Target::from(Source::Leaf(Leaf::A(1))), Target::A(1));
+            assert_eq!(Target::from(Source::Leaf(Leaf::B("b".to_owned()))), Target::B("b".to_owned()));
+            assert_eq!(Target::from(Source::Pair(Leaf::A(1), 2)), Target::Pair("1".to_owned(), 2));
+            assert_eq!(Target::from(Source::Plain(3)), Target::Plain(3));
+        }
+
+        #[test]
+        fn test_borrowed_nested_patterns() {
+            assert_eq!(Target::from(&Source::Leaf(Leaf::A(1))), Target::A(1));
+            assert_eq!(Target::from(&Source::Pair(Leaf::A(1), 2)), Target::Pair(String::new(), 2));
+            assert_eq!(Target::from(&Source::Plain(3)), Target::Plain(3));
+        }
+    }
+
+    mod borrowed {
+        test_types!(Sub, Full);
+
+        map_enum!(&Sub > Full {
+            I,
+            S,
+            U > Ub,
+            R > Ra,
+            M > Ma = (a, b),
+            @unit Unit,
+        });
+
+        #[allow(dead_code)]
+        #[derive(Debug, PartialEq, Eq)]
+        enum Kind {
+            Number,
+            Text(usize),
+            Other,
+        }
+
+        map_enum!(&Sub > Kind {
+            I = (_i) { Kind::Number },
+            S = (s) { Kind::Text(s.len()) },
+        } |_ignored| {
+            Kind::Other
+        });
+
+        #[test]
+        fn test_cloned_payloads() {
+            let s = Sub::S("s".to_owned());
+            assert_eq!(Full::from(&s), Full::S("s".to_owned()));
+            assert_eq!(Full::from(&Sub::M(1, 2)), Full::Ma(1, 2));
+            assert_eq!(Full::from(&Sub::Unit), Full::Unit);
+            assert_eq!(s, Sub::S("s".to_owned()), "source to still be usable");
+        }
+
+        map_enum!(&Sub > u64 {
+            U(u) => *u,
+            S(s) => s.len() as u64,
+        } |_ignored| {
+            0
+        });
+
+        #[test]
+        fn test_borrowed_values() {
+            assert_eq!(u64::from(&Sub::U(4)), 4);
+            assert_eq!(u64::from(&Sub::S("ab".to_owned())), 2);
+            assert_eq!(u64::from(&Sub::Unit), 0);
+        }
+
+        #[test]
+        fn test_borrowed_blocks() {
+            assert_eq!(Kind::from(&Sub::I(1)), Kind::Number);
+            assert_eq!(Kind::from(&Sub::S("abc".to_owned())), Kind::Text(3));
+            assert_eq!(Kind::from(&Sub::Unit), Kind::Other);
+        }
+    }
+
+    mod many {
+        #![deny(unreachable_patterns)]
+
+        test_types!(Sub, Full);
+
+        #[allow(dead_code)]
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        enum Other {
+            I(i32),
+            S(String),
+            Extra,
+        }
+
+        map_enum!(
+            Sub {
+                U > Ub,
+                R > Ra,
+                M > Ma = (a, b),
+                @unit Unit,
+            },
+            Other {
+                I > Ib,
+                S(s) if s.is_empty() => Full::Unit,
+            } |_ignored| {
+                Full::Unit
+            }
+            > Full {
+                I,
+                S,
+            }
+        );
+
+        #[test]
+        fn test_shared_mappings() {
+            assert_eq!(Full::from(Sub::I(1)), Full::I(1));
+            assert_eq!(Full::from(Sub::S("s".to_owned())), Full::S("s".to_owned()));
+            assert_eq!(Full::from(Other::S("s".to_owned())), Full::S("s".to_owned()));
+        }
+
+        #[test]
+        fn test_overrides() {
+            assert_eq!(Full::from(Sub::U(2)), Full::Ub(2));
+            assert_eq!(Full::from(Sub::Unit), Full::Unit);
+            assert_eq!(Full::from(Other::I(1)), Full::Ib(1), "override to shadow shared mapping");
+            assert_eq!(Full::from(Other::S(String::new())), Full::Unit, "guarded override to match first");
+            assert_eq!(Full::from(Other::Extra), Full::Unit, "own catch-all to be used");
+        }
+    }
+
+    mod many_shared_catch {
+        test_types!(Sub, Full);
+
+        #[allow(dead_code)]
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        enum Other {
+            I(i32),
+            Extra,
+        }
+
+        map_enum!(Sub, Other > Full {
+            I > Ib,
+        } |_ignored| {
+            Full::Unit
+        });
+
+        #[test]
+        fn test_shared_catch() {
+            assert_eq!(Full::from(Sub::I(1)), Full::Ib(1));
+            assert_eq!(Full::from(Sub::U(1)), Full::Unit);
+            assert_eq!(Full::from(Other::Extra), Full::Unit);
+        }
+    }
+
+    mod generic {
+        use std::fmt::Debug;
+
+        #[derive(Debug, PartialEq, Eq)]
+        enum Inner<E> {
+            Storage(E),
+            Missing,
+        }
+        #[derive(Debug, PartialEq, Eq)]
+        enum Outer<E> {
+            Storage(E),
+            NotFound,
+        }
+        #[derive(Debug, PartialEq, Eq)]
+        enum Wide<'a, E, F> {
+            Storage(E, F),
+            Named(&'a str),
+        }
+        #[derive(Debug, PartialEq, Eq)]
+        enum WideOuter<'a, E, F> {
+            Storage(E, F),
+            Named(&'a str),
+        }
+
+        map_enum!(impl<E> Inner<E> > Outer<E> {
+            Storage,
+            @unit Missing > NotFound,
+        });
+
+        map_enum!(impl<'a, E: Into<Vec<u8>>, F> Wide<'a, E, F> > WideOuter<'a, E, F> where F: Debug + Clone {
+            Storage = (e, f),
+            Named,
+        });
+
+        #[test]
+        fn test_generic_from() {
+            assert_eq!(Outer::from(Inner::Storage(3u8)), Outer::Storage(3u8));
+            assert_eq!(Outer::<u8>::from(Inner::Missing), Outer::NotFound);
+            assert_eq!(
+                WideOuter::from(Wide::Storage("a", 1u8)),
+                WideOuter::Storage("a", 1u8),
+            );
+            assert_eq!(WideOuter::<&str, u8>::from(Wide::Named("n")), WideOuter::Named("n"));
+        }
+    }
 }
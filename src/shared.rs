@@ -1,2 +1,63 @@
 #[macro_export]
 macro_rules! as_pat { ($p:pat) => { $p } }
+
+/// Splits the generic parameters off the front of an `impl<...>` header and hands them, along
+/// with the rest of the tokens, to a callback macro.
+///
+/// The callback receives `$($prefix)* @generics [params] rest...`. Nested angle brackets inside
+/// of bounds (`T: Into<Vec<u8>>`) are tracked so that only the closing `>` of the header ends
+/// the parameter list.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! split_generics {
+    // Header is done, hand everything back.
+    (@callback [$($cb:tt)+] [$($prefix:tt)*] [$($acc:tt)*] [] > $($rest:tt)*) => {
+        $($cb)+! { $($prefix)* @generics [$($acc)*] $($rest)* }
+    };
+    // `>>` is a single token, so it needs to close two levels at once (or one level and the
+    // header itself).
+    (@callback [$($cb:tt)+] [$($prefix:tt)*] [$($acc:tt)*] [<] >> $($rest:tt)*) => {
+        $($cb)+! { $($prefix)* @generics [$($acc)* >] $($rest)* }
+    };
+    (@callback [$($cb:tt)+] [$($prefix:tt)*] [$($acc:tt)*] [< < $($depth:tt)*] >> $($rest:tt)*) => {
+        $crate::split_generics! {
+            @callback [$($cb)+] [$($prefix)*] [$($acc)* >>] [$($depth)*] $($rest)*
+        }
+    };
+    (@callback [$($cb:tt)+] [$($prefix:tt)*] [$($acc:tt)*] [< $($depth:tt)*] > $($rest:tt)*) => {
+        $crate::split_generics! {
+            @callback [$($cb)+] [$($prefix)*] [$($acc)* >] [$($depth)*] $($rest)*
+        }
+    };
+    (@callback [$($cb:tt)+] [$($prefix:tt)*] [$($acc:tt)*] [$($depth:tt)*] < $($rest:tt)*) => {
+        $crate::split_generics! {
+            @callback [$($cb)+] [$($prefix)*] [$($acc)* <] [< $($depth)*] $($rest)*
+        }
+    };
+    (@callback [$($cb:tt)+] [$($prefix:tt)*] [$($acc:tt)*] [$($depth:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::split_generics! {
+            @callback [$($cb)+] [$($prefix)*] [$($acc)* $next] [$($depth)*] $($rest)*
+        }
+    };
+}
+
+/// Splits a trailing `where` clause off of the tokens and hands both to a callback macro.
+///
+/// The callback receives `$($prefix)* [where clause] tokens...`, with an empty where clause if
+/// there is none. `where` can't show up in an expression or type outside of a nested group, so
+/// the first one found at the top level starts the clause.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! split_where {
+    (@callback [$($cb:tt)+] [$($prefix:tt)*] [$($acc:tt)*] where $($where:tt)*) => {
+        $($cb)+! { $($prefix)* [$($where)*] $($acc)* }
+    };
+    (@callback [$($cb:tt)+] [$($prefix:tt)*] [$($acc:tt)*]) => {
+        $($cb)+! { $($prefix)* [] $($acc)* }
+    };
+    (@callback [$($cb:tt)+] [$($prefix:tt)*] [$($acc:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::split_where! {
+            @callback [$($cb)+] [$($prefix)*] [$($acc)* $next] $($rest)*
+        }
+    };
+}
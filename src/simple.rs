@@ -51,21 +51,51 @@
 ///
 /// let a: A = C.into();
 /// ```
+///
+/// All three can be made generic by prefixing them with `impl<...>`, with an optional `where`
+/// clause at the very end.
+///
+/// ```
+/// use treeerror::from;
+///
+/// struct Wrapper<T>(T);
+///
+/// enum Root<T> {
+///     Wrapped(Wrapper<T>),
+/// }
+/// from!(impl<T> Root<T> = Wrapped(Wrapper<T>));
+/// from!(impl<T> Root<T> = |t: T| Root::Wrapped(Wrapper(t)) where T: std::error::Error);
+///
+/// let root: Root<std::fmt::Error> = std::fmt::Error.into();
+/// ```
 #[macro_export]
 macro_rules! from {
-    ($to:ty = $via:ident ( $from:ty )) => (
-        $crate::from!($to = |e: $from| {
+    // Generic impls, the generics and `where` clause get split off before landing in the rules
+    // below.
+    (impl < $($rest:tt)+) => (
+        $crate::split_generics! {
+            @callback [$crate::from] [@generic] [] [] $($rest)+
+        }
+    );
+    (@generic @generics [$($g:tt)*] $($rest:tt)+) => (
+        $crate::split_where! {
+            @callback [$crate::from] [@generics [$($g)*]] [] $($rest)+
+        }
+    );
+
+    ($(@generics [$($g:tt)*] [$($w:tt)*])? $to:ty = $via:ident ( $from:ty )) => (
+        $crate::from!($(@generics [$($g)*] [$($w)*])? $to = |e: $from| {
             Self::$via(e.into())
         });
     );
-    ($to:ty = $from:ty > $via:ty) => (
-        $crate::from!($to = |e: $from| {
+    ($(@generics [$($g:tt)*] [$($w:tt)*])? $to:ty = $from:ty > $via:ty) => (
+        $crate::from!($(@generics [$($g)*] [$($w)*])? $to = |e: $from| {
             <$via>::from(e).into()
         });
     );
-    ($to:ty = |$var:ident: $from:ty| $($tokens:expr)*) => (
+    ($(@generics [$($g:tt)*] [$($w:tt)*])? $to:ty = |$var:ident: $from:ty| $($tokens:expr)*) => (
         #[automatically_derived]
-        impl From<$from> for $to {
+        impl$(<$($g)*>)? From<$from> for $to $(where $($w)*)? {
             fn from($var: $from) -> Self {
                 $($tokens)*
             }
@@ -1,364 +1,299 @@
-// TODO: Consider how to process tress.
+/// Declares a whole tree of error types at once, along with the `From` impls that convert every
+/// node into all of the nodes above it.
+///
+/// Each node is one of:
+/// - `Name { ... }`, an enum with a variant per child.
+/// - `Name @unit`, a unit struct, wrapped by the parent as `Name(Name)`.
+/// - `Name @flatunit`, a plain unit variant on the parent, with nothing to convert from.
+/// - `Name(Type)`, a parent variant wrapping an existing type.
+/// - `Name @shared(Type)`, a parent variant wrapping an existing type in an `Arc`.
+/// - `Name @dyn`, a parent variant wrapping a `Box<dyn Error + Send + Sync>`.
+/// - `Name @mount(path::to::Root)`, a parent variant wrapping a tree exported from elsewhere.
+///
+/// Attributes on a node are placed on the type generated for it.
+/// ```
+/// use treeerror::treeerror;
+///
+/// treeerror! {
+///     #[derive(Debug)]
+///     Root {
+///         #[derive(Debug)]
+///         Missing @unit,
+///         Unknown @flatunit,
+///         Message(String),
+///     }
+/// }
+///
+/// assert!(matches!(Root::from(Missing), Root::Missing(Missing)));
+/// assert!(matches!(Root::from("oops".to_owned()), Root::Message(_)));
+/// ```
+///
+/// Attributes meant for the node's variant in its parent instead, like `#[error(...)]` for
+/// `thiserror` or `#[serde(...)]`, go in a `#[variant(...)]`. Everything inside of it is placed on
+/// the variant, which works for nodes that don't get a type of their own as well.
+/// ```
+/// use treeerror::treeerror;
+///
+/// treeerror! {
+///     #[derive(Debug)]
+///     Root {
+///         #[variant(doc = "Nothing was found.")]
+///         #[derive(Debug)]
+///         Missing @unit,
+///         #[variant(doc = "Something went wrong.", allow(unused))]
+///         Unknown @flatunit,
+///     }
+/// }
+/// ```
+///
+/// Attributes in a `#![...]` header at the top are placed on every type in the invocation, ahead
+/// of the node's own attributes. Nodes marked `#[no_inherit]` opt out of them.
+/// ```
+/// use treeerror::treeerror;
+///
+/// treeerror! {
+///     #![derive(Debug, Clone, PartialEq)]
+///
+///     Root {
+///         Inner {
+///             Missing @unit,
+///         },
+///         #[no_inherit]
+///         #[derive(Debug, Clone, PartialEq, Eq)]
+///         Unique @unit,
+///     }
+/// }
+///
+/// assert_eq!(Root::from(Missing), Root::Inner(Inner::Missing(Missing)));
+/// assert_eq!(Unique, Unique.clone());
+/// ```
+///
+/// `#[cfg(...)]` on a node gates its variant in the parent and every `From` impl involving it,
+/// along with the types of the whole subtree below it, so a branch can be left out entirely.
+/// ```
+/// use treeerror::treeerror;
+///
+/// treeerror! {
+///     Storage {
+///         #[cfg(feature = "s3")]
+///         S3 {
+///             Denied(s3::AccessDenied),
+///         },
+///         Disk(std::io::Error),
+///     }
+/// }
+/// ```
+///
+/// `@dyn` leaves catch any error that isn't part of the tree. Their parent gets a blanket
+/// `From<E>` for every `E: Error + Send + Sync + 'static`, which goes no further up the tree, so
+/// the parent and all of its other children must be local types that don't implement `Error`.
+/// ```
+/// use treeerror::treeerror;
+///
+/// treeerror! {
+///     #[derive(Debug)]
+///     Root {
+///         #[derive(Debug)]
+///         Parse {
+///             Other @dyn,
+///         },
+///     }
+/// }
+///
+/// let parse: Parse = std::fmt::Error.into();
+/// assert!(matches!(Root::from(parse), Root::Parse(Parse::Other(_))));
+/// ```
+///
+/// `@inline` on a node with children adds its children to its parent as variants of their own,
+/// instead of wrapping the node in a variant. The node's enum is still generated, along with a
+/// `From` impl mapping it into the parent variant by variant. Its children convert into it and
+/// into the parent directly, so a `@dyn` child makes both of them catch everything.
+/// ```
+/// use treeerror::treeerror;
+///
+/// treeerror! {
+///     #[derive(Debug)]
+///     Root {
+///         #[derive(Debug)]
+///         Io @inline {
+///             #[derive(Debug)]
+///             NotFound @unit,
+///             Denied(String),
+///         },
+///         Unknown @flatunit,
+///     }
+/// }
+///
+/// assert!(matches!(Root::from(NotFound), Root::NotFound(NotFound)));
+/// assert!(matches!(Root::from(Io::Denied("no".to_owned())), Root::Denied(_)));
+/// ```
+///
+/// `Name @shared(Type)` is a leaf like `Name(Type)`, except that its variant keeps the payload
+/// in an `Arc`, so that trees wrapping types that aren't `Clone`, like `std::io::Error`, can still
+/// derive it. It's still converted from `Type`, and every enum above the variant gets an
+/// `as_name()` borrowing the payload from it, unless another `@shared` leaf below that enum has
+/// the same name. `@backtrace` can't be combined with `Clone`, since `Backtrace` isn't.
+/// ```
+/// use treeerror::treeerror;
+///
+/// treeerror! {
+///     #[derive(Debug, Clone)]
+///     Root {
+///         Io @shared(std::io::Error),
+///     }
+/// }
+///
+/// let error = Root::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+/// let copy = error.clone();
+/// assert_eq!(copy.as_io().unwrap().kind(), std::io::ErrorKind::NotFound);
+/// ```
+///
+/// Putting `@location` on a root records where every leaf below it was converted into the tree.
+/// Leaf variants get an extra `&'static Location<'static>` field, which is filled in by the
+/// (`#[track_caller]`) `From` impls, so errors converted with `?` point at the `?`. Every enum in
+/// the tree gets a `location()` that finds it.
+/// ```
+/// use treeerror::treeerror;
+///
+/// treeerror! {
+///     Root @location {
+///         Inner {
+///             Message(String),
+///         },
+///         Unknown @flatunit,
+///     }
+/// }
+///
+/// let (error, line) = (Root::from("oops".to_owned()), line!());
+/// assert_eq!(error.location().unwrap().line(), line);
+/// assert!(Root::Unknown.location().is_none());
+/// ```
+///
+/// `@backtrace` works the same way, adding a `Backtrace` to every leaf variant instead, and a
+/// `backtrace()` to every enum. It's captured with `Backtrace::capture`, so it's only actually
+/// collected when `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` turn it on. Both can be used at once,
+/// as in `Root @location @backtrace { ... }`.
+/// ```
+/// use treeerror::treeerror;
+///
+/// treeerror! {
+///     Root @backtrace {
+///         Message(String),
+///     }
+/// }
+///
+/// use std::backtrace::{Backtrace, BacktraceStatus};
+///
+/// let error = Root::from("oops".to_owned());
+/// let enabled = Backtrace::capture().status() == BacktraceStatus::Captured;
+/// assert_eq!(error.backtrace().is_some(), enabled);
+/// ```
+///
+/// `@context` lets errors pick up context on their way up the tree, with
+/// [`ResultExt`](crate::ResultExt). Leaves get a [`ContextStack`](crate::ContextStack) to keep it
+/// in, every enum gets `context()` and `context_mut()` to reach it, and the root gets a `From`
+/// impl for [`Context`](crate::Context) as well as `into_context()`, to move it back out into
+/// something that renders it. Flat unit variants have nowhere to keep context, so it's dropped,
+/// and the root can't have a direct `@dyn` child, since both would convert from `Context`.
+/// ```
+/// use treeerror::{treeerror, ResultExt};
+///
+/// treeerror! {
+///     #[derive(Debug)]
+///     Root @context {
+///         Message(String),
+///     }
+/// }
+///
+/// fn load() -> Result<(), Root> {
+///     Err("no such file".to_owned()).context("loading config")?;
+///     Ok(())
+/// }
+///
+/// let context = load().unwrap_err().into_context();
+/// assert_eq!(context.context().to_string(), "loading config");
+/// assert!(matches!(context.error(), Root::Message(message, _) if message == "no such file"));
+/// ```
+///
+/// `@modules` declares every enum in its own module, named after it in snake case and nested the
+/// same way the tree is, so that different subtrees can reuse names. Leaves are declared in their
+/// parent's module, and only the root is re-exported from the invoking module. The modules
+/// glob-import their parent, so wrapped types resolve the same way they would outside of them,
+/// except for paths starting with `self::` or `super::`.
+/// ```
+/// use treeerror::treeerror;
+///
+/// treeerror! {
+///     Root @modules {
+///         Config {
+///             NotFound @unit,
+///         },
+///         Cache {
+///             NotFound @unit,
+///         },
+///         Message(String),
+///     }
+/// }
+///
+/// assert!(matches!(Root::from(root::config::NotFound), Root::Config(root::config::Config::NotFound(_))));
+/// assert!(matches!(Root::from(root::cache::NotFound), Root::Cache(root::cache::Cache::NotFound(_))));
+/// ```
+///
+/// `@names("...")` names every type generated for the tree, the root included, after a template
+/// where `{}` stands in for the node's name. Variants keep the plain names, and modules are still
+/// named after them.
+/// ```
+/// use treeerror::treeerror;
+///
+/// treeerror! {
+///     Storage @names("{}Error") {
+///         Disk {
+///             Full @unit,
+///         },
+///         Timeout @unit,
+///     }
+/// }
+///
+/// let error = StorageError::from(FullError);
+/// assert!(matches!(error, StorageError::Disk(DiskError::Full(FullError))));
+/// assert!(matches!(StorageError::from(TimeoutError), StorageError::Timeout(_)));
+/// ```
+///
+/// Trees can be mounted into trees declared elsewhere with `Name @mount(path::to::Root)`. The
+/// mounted root is wrapped like any other leaf, and everything converting into it converts into
+/// the new tree as well, including the sources of trees mounted into it in turn. The path has to
+/// name the root where it was declared, since re-exports of it don't carry the metadata along.
+/// Mounting a tree from another crate takes `@export` on its root, and exported roots need names
+/// that are unique within their crate. Nodes behind a `#[cfg(...)]` are mounted as they're
+/// configured where the tree is declared. A type converting into the new tree both directly and
+/// through the mounted one is an error naming both paths, as long as it's written the same way
+/// everywhere, like `std::io::Error` or `String`. Anything else only shows up as conflicting
+/// impls.
+/// ```
+/// use treeerror::treeerror;
+///
+/// mod storage {
+///     treeerror::treeerror! {
+///         Storage {
+///             Full @unit,
+///             Io(std::io::Error),
+///         }
+///     }
+/// }
+///
+/// treeerror! {
+///     App {
+///         Storage @mount(storage::Storage),
+///         Message(String),
+///     }
+/// }
+///
+/// assert!(matches!(App::from(storage::Full), App::Storage(storage::Storage::Full(_))));
+/// ```
 #[macro_export]
 macro_rules! treeerror {
-    {
-        $(
-            $(#[$($node_cfg:tt)+])*
-            $node:ident $(@$wrapper_modifier:ident)? $({ $($subtree:tt)+ })? $(($wrapped:ty))?
-        ),+ $(,)?
-    } => {
-        $(
-            $crate::treeerror! {
-                @classes
-                $(#[$($node_cfg)+])*
-                $node $(@$wrapper_modifier)? $({ $($subtree)+ })? $(($wrapped))?
-            }
-
-            $crate::treeerror! {
-                @froms
-                ()
-                $node $(@$wrapper_modifier)? $({ $($subtree)+ })? $(($wrapped))?
-            }
-        )+
-    };
-
-    {
-        @classes
-        $(
-            $(#[$($node_cfg:tt)+])*
-            $node:ident $(@$node_modifier:ident)? $({
-                $(
-                    $(#[$($subnode_cfg:tt)+])*
-                    $subnode:ident $(@$subnode_modifier:ident)? $({ $($subtree:tt)+ })? $(($subwrapped:ty))?
-                ),* $(,)?
-            })? $(($wrapped:ty))?
-        ),+
-    } => {
-        $(
-            // $node decl
-            $crate::treeerror! {
-                @class
-                $(#[$($node_cfg)+])*
-                $node $(@$node_modifier)? $({
-                    $(
-                        $subnode $(@$subnode_modifier)? $({ $($subtree)+ })? $(($subwrapped))?
-                    ),*
-                })? $(($wrapped))?
-            }
-            // $node children decl
-            $($($crate::treeerror! {
-                @classes
-                $(#[$($subnode_cfg)+])*
-                $subnode $(@$subnode_modifier)? $({ $($subtree)+ })? $(($subwrapped))?
-            })*)?
-        )+
-    };
-
-    // Shows an error message so we don't get too far without knowing why it broke.
-    // Unit struct wrappers
-    {
-        @class
-        $(#[$($node_cfg:tt)+])*
-        $node:ident @unit { $($subtree:tt)+ }
-    } => {
-        compiler_warn!(concat!($node, " was provided a subtree despite it having a unit modifier, please only have one of the two. assuming unit struct"));
-        $crate::treeerror! {
-            @class
-            $(#[$($node_cfg)*])*
-            $node @unit
-        }
-    };
-    {
-        @class
-        $(#[$($node_cfg:tt)+])*
-        $node:ident @unit
-    } => {
-        $(#[$($node_cfg)+])*
-        #[allow(dead_code)]
-        pub struct $node;
-    };
-    // Generate enum, needs to munch variant by variant because stupid rules
-    {
-        @class
-        $(#[$($node_cfg:tt)+])*
-        $node:ident {
-            $(
-                $(#[$($subnode_cfg:tt)+])*
-                $subnode:ident $(@$modifier:ident)? $({ $($subtree:tt)+ })? $(($subwrapped:ty))?
-            ),* $(,)?
-        }
-    } => {
-        $crate::treeerror! {
-            @enum_class {
-                $(#[$($node_cfg)+])*
-                $node 
-            }
-            @variants {
-                $($subnode $(@$modifier)? $(($subwrapped))?),*
-            }
-            @processed {}
-        }
-    };
-    // For enum variants wrapping explicit types, there's no other class that gets wrapped down
-    // here -- ignore!
-    {
-        @class
-        $(#[$($node_cfg:tt)+])*
-        $node:ident ($wrapped:ty)
-    } => {};
-    // Flatunit is handled elsewhere (in enum_class) -- ignore!
-    {
-        @class
-        $(#[$($node_cfg:tt)+])*
-        $node:ident @flatunit
-    } => {};
-    // Final few catchalls in case user put in something weird
-    {
-        @class
-        $(#[$($node_cfg:tt)+])*
-        $node:ident @$some_modifier:ident $({ $($subtree:tt)+ })? $(($wrapped:ty))?
-    } => {
-        $crate::treeerror! {
-            @class
-            $(#[$($node_cfg)*])*
-            $node @unit
-        }
-    };
-    {
-        @class
-        $(#[$($node_cfg:tt)+])*
-        $node:ident { $($subtree:tt)+ } ($wrapped:ty)
-    } => {
-        compile_error!(concat!(stringify!($node), " couldn't be parsed."));
-    };
-
-    // Simple enum generator that munches variant by variant
-    // forced to do it because Rust macros have the stupid rules must generate valid expression
-    // rule when the token tree is long enough to be parsed.
-    //
-    // And we need to do this mutual recursion thing because dumbness
-    {
-        @enum_class {
-            $(#[$($node_cfg:tt)+])*
-            $node:ident 
-        }
-        @variants {
-            $subnode:ident ($wrapped:ty) $(,)?
-            $($subnode_tail:ident $(@$modifier_tail:ident)? $(($wrapped_tail:ty))?),*
-        }
-        @processed {
-            $($processed:tt)*
-        }
-    } => {
-        $crate::treeerror! {
-            @enum_class {
-                $(#[$($node_cfg)+])*
-                $node
-            }
-            @variants {
-                $($subnode_tail $(@$modifier_tail)? $(($wrapped_tail))?),*
-            }
-            @processed {
-                $($processed)*
-                $subnode($wrapped),
-            }
-        }
-    };
-    {
-        @enum_class {
-            $(#[$($node_cfg:tt)+])*
-            $node:ident 
-        }
-        @variants {
-            $subnode:ident @unit $(,)?
-            $($subnode_tail:ident $(@$modifier_tail:ident)? $(($wrapped_tail:ty))?),*
-        }
-        @processed {
-            $($processed:tt)*
-        }
-    } => {
-        $crate::treeerror! {
-            @enum_class {
-                $(#[$($node_cfg)+])*
-                $node
-            }
-            @variants {
-                $($subnode_tail $(@$modifier_tail)? $(($wrapped_tail))?),*
-            }
-            @processed {
-                $($processed)*
-                $subnode($subnode),
-            }
-        }
-    };
-    {
-        @enum_class {
-            $(#[$($node_cfg:tt)+])*
-            $node:ident
-        }
-        @variants {
-            $subnode:ident @flatunit $(,)?
-            $($subnode_tail:ident $(@$modifier_tail:ident)? $(($wrapped_tail:ty))?),*
-        }
-        @processed {
-            $($processed:tt)*
-        }
-    } => {
-        $crate::treeerror! {
-            @enum_class {
-                $(#[$($node_cfg)+])*
-                $node
-            }
-            @variants {
-                $($subnode_tail $(@$modifier_tail)? $(($wrapped_tail))?),*
-            }
-            @processed {
-                $($processed)*
-                $subnode,
-            }
-        }
-    };
-    {
-        @enum_class {
-            $(#[$($node_cfg:tt)+])*
-            $node:ident
-        }
-        @variants {
-            $subnode:ident $(,)?
-            $($subnode_tail:ident $(@$modifier_tail:ident)? $(($wrapped_tail:ty))?),*
-        }
-        @processed {
-            $($processed:tt)*
-        }
-    } => {
-        $crate::treeerror! {
-            @enum_class {
-                $(#[$($node_cfg)+])*
-                $node
-            }
-            @variants {
-                $($subnode_tail $(@$modifier_tail)? $(($wrapped_tail))?),*
-            }
-            @processed {
-                $($processed)*
-                $subnode($subnode),
-            }
-        }
-    };
-    {
-        @enum_class {
-            $(#[$($node_cfg:tt)+])*
-            $node:ident
-        }
-        @variants {}
-        @processed {
-            $($processed:tt)*
-        }
-    } => {
-        $(#[$($node_cfg)+])*
-        #[allow(dead_code)]
-        pub enum $node {
-            $($processed)*
-        }
-    };
-
-    {
-        @froms
-        ($($parents:ident),* $(,)?)
-    } => {
-    };
-    // Peel off one by one, needs to be a separate rule due to duplicate comma parsing
-    {
-        @froms
-        ($($parents:ident),* $(,)?)
-        $(#[$($node_cfg:tt)+])*
-        $node:ident $(@$node_modifier:ident)? $({ $($subtree:tt)+ })? $(($wrapped:ty))? $(,)?
-        $($(
-            $(#[$($tail_cfg:tt)+])*
-            $tail_nodes:ident $(@$tail_modifier:ident)?  $({ $($tail_subtree:tt)+ })? $(($tail_wrapped:ty))?
-        ),+ $(,)?)?
-    } => {
-        $crate::treeerror! {
-            @maybe_from_impls $($node_modifier)?
-            ($node, $($parents),*)
-            $(($wrapped))?
-        }
-        $crate::treeerror! {
-            @froms
-            ($node, $($parents),*)
-            $($($subtree)+)?
-        }
-        $crate::treeerror! {
-            @froms
-            ($($parents),*)
-            $($(
-                $tail_nodes $(@$tail_modifier)?  $({ $($tail_subtree)+ })? $(($tail_wrapped))?
-            ),+)?
-        }
-    };
-
-    {
-        @maybe_from_impls flatunit
-        ($($node:ident),* $(,)?)
-        $(($wrapped:ty))?
-    } => {};
-    {
-        @maybe_from_impls unit
-        ($($node:ident),* $(,)?)
-        $(($wrapped:ty))?
-    } => {
-        $crate::treeerror! {
-            @from_impls
-            ($($node),*)
-            $(($wrapped))?
-        }
-    };
-    {
-        @maybe_from_impls
-        ($($node:ident),* $(,)?)
-        $(($wrapped:ty))?
-    } => {
-        $crate::treeerror! {
-            @from_impls
-            ($($node),*)
-            $(($wrapped))?
-        }
-    };
-
-    {
-        @from_impls
-        ()
-        $(($wrapped:ty))?
-    } => {};
-    {
-        @from_impls
-        ($goal:ident $(,)?)
-        $(($wrapped:ty))?
-    } => {};
-    {
-        @from_impls
-        ($node:ident, $goal:ident $(,)?)
-        ($wrapped:ty)
-    } => {
-        $crate::from!($goal = $node($wrapped));
-    };
-    {
-        @from_impls
-        ($node:ident, $goal:ident $(,)?)
-    } => {
-        $crate::from!($goal = $node($node));
-    };
-    {
-        @from_impls
-        ($node:ident, $via:ident, $goal:ident $(,)? $($($tail:ident),+ $(,)?)?)
-        $(($wrapped:ty))?
-    } => {
-        $crate::from!($goal = $node > $via);
-        $crate::treeerror! {
-            @from_impls
-            ($node , $via $(, $($tail),+)?)
-            $(($wrapped))?
-        }
+    ($($tree:tt)*) => {
+        $crate::expand_tree! { $($tree)* }
     };
 }
 
@@ -435,4 +370,393 @@ mod test {
         assert_eq!(format!("{:?}", Hello::from("hi".to_owned())), "Terminal(\"hi\")");
         assert_eq!(format!("{:?}", Hello::from("hi")), "LifetimeTerminal(\"hi\")");
     }
+
+    crate::treeerror! {
+        #[derive(Debug)]
+        Loading {
+            #[derive(Debug)]
+            Parsing {
+                #[derive(Debug)]
+                Syntax @unit,
+                Other @dyn,
+            },
+            Io(std::io::Error),
+        }
+    }
+
+    #[test]
+    fn test_dyn_leaf() {
+        let parsing = Parsing::from(std::fmt::Error);
+        assert_eq!(format!("{:?}", parsing), "Other(Error)");
+        assert_eq!(format!("{:?}", Loading::from(parsing)), "Parsing(Other(Error))");
+        assert_eq!(format!("{:?}", Loading::from(Syntax)), "Parsing(Syntax(Syntax))");
+        let io = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert!(matches!(Loading::from(io), Loading::Io(_)));
+    }
+
+    crate::treeerror! {
+        #[derive(Debug)]
+        Located @location {
+            #[derive(Debug)]
+            Inner {
+                Boxed @dyn,
+            },
+            Message(String),
+            #[derive(Debug)]
+            Missing @unit,
+            Unknown @flatunit,
+        }
+    }
+
+    crate::treeerror! {
+        #[derive(Debug)]
+        Traced @location @backtrace {
+            #[derive(Debug)]
+            Nested {
+                Message(String),
+            },
+            Unknown @flatunit,
+        }
+    }
+
+    #[test]
+    fn test_backtrace() {
+        let traced = Traced::from("hi".to_owned());
+        assert!(matches!(traced, Traced::Nested(Nested::Message(_, _, _))));
+        assert!(traced.location().is_some());
+        let enabled = std::backtrace::Backtrace::capture().status() == std::backtrace::BacktraceStatus::Captured;
+        assert_eq!(traced.backtrace().is_some(), enabled);
+        assert!(Traced::Unknown.backtrace().is_none());
+    }
+
+    crate::treeerror! {
+        #[derive(Debug)]
+        Explained @context @location {
+            #[derive(Debug)]
+            Config {
+                Missing(String),
+            },
+            Unknown @flatunit,
+        }
+    }
+
+    #[test]
+    fn test_context() {
+        use crate::ResultExt;
+        use std::error::Error;
+
+        #[derive(Debug)]
+        struct Shown(Explained);
+        impl std::fmt::Display for Shown {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{:?}", self.0)
+            }
+        }
+        impl Error for Shown {}
+
+        fn load() -> Result<(), Explained> {
+            Err("config.toml".to_owned()).context("loading config")?;
+            Ok(())
+        }
+        fn start() -> Result<(), Explained> {
+            load().context("starting up")?;
+            Ok(())
+        }
+        let explained = start().unwrap_err();
+        assert!(explained.location().is_some());
+        assert_eq!(explained.context().iter().collect::<Vec<_>>(), ["loading config", "starting up"]);
+
+        let context = explained.into_context();
+        assert!(context.error().context().is_empty());
+        let (error, stack) = context.into_parts();
+        let context = crate::Context::new(Shown(error), stack);
+        assert_eq!(context.to_string(), "starting up: loading config");
+        assert!(context.source().unwrap().to_string().starts_with("Config(Missing(\"config.toml\""));
+
+        let flat = Explained::from(Err::<(), _>(Explained::Unknown).context("ignored").unwrap_err());
+        assert!(flat.context().is_empty());
+    }
+
+    pub enum Legacy {
+        Gone,
+        Text(String),
+    }
+
+    crate::treeerror! {
+        #[derive(Debug, crate::MapEnum)]
+        #[map_from(Legacy)]
+        Modern {
+            #[variant(map(from = Gone))]
+            Missing @flatunit,
+            #[variant(map(from = Text))]
+            Message(String),
+        }
+    }
+
+    #[test]
+    fn test_variant_attrs() {
+        assert_eq!(format!("{:?}", Modern::from(Legacy::Gone)), "Missing");
+        assert_eq!(format!("{:?}", Modern::from(Legacy::Text("hi".to_owned()))), "Message(\"hi\")");
+    }
+
+    crate::treeerror! {
+        #![derive(Debug, Clone)]
+        #![allow(clippy::enum_variant_names)]
+
+        Inherited {
+            InheritedA {
+                InheritedB @unit,
+            },
+            #[no_inherit]
+            #[derive(Debug, Clone, Copy)]
+            NotInherited @unit,
+        }
+    }
+
+    #[test]
+    fn test_inherited_attrs() {
+        let inherited = Inherited::from(InheritedB);
+        assert_eq!(format!("{:?}", inherited.clone()), "InheritedA(InheritedB(InheritedB))");
+        let not_inherited = NotInherited;
+        assert_eq!(format!("{:?}", Inherited::from(not_inherited)), "NotInherited(NotInherited)");
+        assert_eq!(format!("{:?}", not_inherited), "NotInherited");
+    }
+
+    crate::treeerror! {
+        #![derive(Debug)]
+
+        Backend @location @context {
+            #[cfg(any())]
+            Remote {
+                Timeout @unit,
+                Denied(DoesNotExist),
+            },
+            #[cfg(any())]
+            Other(DoesNotExist),
+            #[cfg(all())]
+            Local {
+                Io(std::io::Error),
+            },
+        }
+    }
+
+    #[test]
+    fn test_cfg() {
+        let backend = Backend::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert!(matches!(backend, Backend::Local(Local::Io(..))));
+        assert!(backend.location().is_some());
+        assert!(backend.context().is_empty());
+    }
+
+    #[test]
+    fn test_location() {
+        let (located, line) = (Located::from("hi".to_owned()), line!());
+        assert_eq!(located.location().map(|l| (l.file(), l.line())), Some((file!(), line)));
+        assert!(matches!(located, Located::Message(_, _)));
+
+        fn missing() -> Result<(), Located> {
+            Err(Missing)?;
+            Ok(())
+        }
+        let line = line!() - 3;
+        assert_eq!(missing().unwrap_err().location().map(|l| l.line()), Some(line));
+
+        let (boxed, line) = (Inner::from(std::fmt::Error), line!());
+        assert_eq!(boxed.location().map(|l| l.line()), Some(line));
+        assert_eq!(Located::Unknown.location(), None);
+    }
+
+    crate::treeerror! {
+        #![derive(Debug)]
+
+        Modular @modules @location {
+            OtherWorld {
+                NotFound @unit,
+                IOError {
+                    NotFound @unit,
+                    Io(std::io::Error),
+                },
+            },
+            NotFound @unit,
+        }
+    }
+
+    #[test]
+    fn test_modules() {
+        use modular::{other_world::{self, io_error}, NotFound};
+
+        let error = Modular::from(io_error::NotFound);
+        assert!(matches!(
+            error,
+            Modular::OtherWorld(other_world::OtherWorld::IOError(io_error::IOError::NotFound(..))),
+        ));
+        assert!(error.location().is_some());
+        let error = Modular::from(other_world::NotFound);
+        assert!(matches!(error, Modular::OtherWorld(other_world::OtherWorld::NotFound(..))));
+        assert!(matches!(Modular::from(NotFound), Modular::NotFound(..)));
+        let io = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert!(matches!(other_world::OtherWorld::from(io), other_world::OtherWorld::IOError(_)));
+    }
+
+    crate::treeerror! {
+        #![derive(Debug)]
+
+        Named @names("My{}Error") @modules {
+            Inner {
+                Missing @unit,
+            },
+            Unknown @flatunit,
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        use named::inner::{MyInnerError, MyMissingError};
+
+        let error = MyNamedError::from(MyMissingError);
+        assert_eq!(format!("{:?}", error), "Inner(Missing(MyMissingError))");
+        assert!(matches!(error, MyNamedError::Inner(MyInnerError::Missing(_))));
+        assert!(matches!(MyNamedError::from(MyInnerError::Missing(MyMissingError)), MyNamedError::Inner(_)));
+    }
+
+    mod storage {
+        crate::treeerror! {
+            #![derive(Debug)]
+
+            Storage @export {
+                Disk {
+                    Full @unit,
+                    Io(std::io::Error),
+                },
+                Timeout @unit,
+                #[cfg(all())]
+                Remote {
+                    Denied @unit,
+                },
+                // Would clash with `App > Message` if it were mounted.
+                #[cfg(any())]
+                Rejected(String),
+            }
+        }
+    }
+
+    crate::treeerror! {
+        #![derive(Debug)]
+
+        App @export @location {
+            Storage @mount(storage::Storage),
+            Message(String),
+        }
+    }
+
+    crate::treeerror! {
+        #![derive(Debug)]
+
+        Service {
+            App @mount(App),
+            Startup @unit,
+        }
+    }
+
+    #[test]
+    fn test_mount() {
+        let app = App::from(storage::Full);
+        assert!(matches!(app, App::Storage(storage::Storage::Disk(storage::Disk::Full(_)), _)));
+        assert!(app.location().is_some());
+        let io = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert!(matches!(App::from(io), App::Storage(storage::Storage::Disk(storage::Disk::Io(_)), _)));
+        assert!(matches!(App::from(storage::Storage::Timeout(storage::Timeout)), App::Storage(..)));
+        let denied = App::from(storage::Denied);
+        assert!(matches!(denied, App::Storage(storage::Storage::Remote(storage::Remote::Denied(_)), _)));
+
+        assert!(matches!(Service::from(storage::Timeout), Service::App(App::Storage(..))));
+        assert!(matches!(Service::from(storage::Denied), Service::App(App::Storage(..))));
+        assert!(matches!(Service::from("hi".to_owned()), Service::App(App::Message(..))));
+        assert!(matches!(Service::from(Startup), Service::Startup(_)));
+    }
+
+    crate::treeerror! {
+        #![derive(Debug)]
+
+        Flattened @location @modules {
+            Io @inline {
+                NotFound @unit,
+                Denied(String),
+                Net {
+                    Timeout @unit,
+                },
+                #[cfg(all())]
+                Deeper @inline {
+                    Unknown @flatunit,
+                    Busy @unit,
+                },
+            },
+            Parse @unit,
+        }
+    }
+
+    #[test]
+    fn test_inline() {
+        use flattened::io::{self, net, Io, NotFound};
+
+        let error = Flattened::from(NotFound);
+        assert!(matches!(error, Flattened::NotFound(NotFound, _)));
+        assert!(error.location().is_some());
+        assert!(matches!(Flattened::from(net::Timeout), Flattened::Net(net::Net::Timeout(..))));
+        assert!(matches!(Flattened::from("denied".to_owned()), Flattened::Denied(..)));
+        assert!(matches!(Flattened::from(io::deeper::Busy), Flattened::Busy(..)));
+
+        let io = Io::from(net::Timeout);
+        assert!(matches!(io, Io::Net(net::Net::Timeout(..))));
+        assert!(matches!(Flattened::from(io), Flattened::Net(_)));
+        assert!(matches!(Flattened::from(Io::Unknown), Flattened::Unknown));
+        let deeper = io::deeper::Deeper::Unknown;
+        assert!(matches!(Io::from(deeper), Io::Unknown));
+        assert!(matches!(Flattened::from(io::deeper::Deeper::Unknown), Flattened::Unknown));
+        assert_eq!(Flattened::Unknown.location(), None);
+    }
+
+    crate::treeerror! {
+        #![derive(Debug, Clone)]
+
+        Broadcast @location @context {
+            Io @shared(std::io::Error),
+            Storage {
+                Disk @shared(std::fmt::Error),
+                Full @flatunit,
+            },
+            // Has a leaf named like `Broadcast > Io`, which `Broadcast` can't forward to.
+            Cache {
+                Io @shared(std::num::ParseIntError),
+            },
+        }
+    }
+
+    #[test]
+    fn test_shared() {
+        use crate::ResultExt;
+
+        let io = || std::io::Error::from(std::io::ErrorKind::NotFound);
+        let error = Broadcast::from(Err::<(), _>(io()).context("reading").unwrap_err());
+        let copies = [error.clone(), error.clone()];
+        for copy in &copies {
+            assert_eq!(copy.as_io().map(|e| e.kind()), Some(std::io::ErrorKind::NotFound));
+            assert_eq!(copy.context().to_string(), "reading");
+            assert_eq!(copy.location(), error.location());
+        }
+        let [Broadcast::Io(a, ..), Broadcast::Io(b, ..)] = &copies else {
+            panic!("expected both copies to be `Io`");
+        };
+        assert!(std::sync::Arc::ptr_eq(a, b));
+
+        let disk = Storage::from(std::fmt::Error);
+        assert_eq!(disk.as_disk(), Some(&std::fmt::Error));
+        assert!(Broadcast::from(disk).as_io().is_none());
+        assert!(Storage::Full.as_disk().is_none());
+        assert_eq!(Broadcast::from(std::fmt::Error).as_disk(), Some(&std::fmt::Error));
+        assert!(Broadcast::from(io()).as_disk().is_none());
+        let parse = "x".parse::<u8>().unwrap_err();
+        assert!(Broadcast::from(parse.clone()).as_io().is_none());
+        assert_eq!(Cache::from(parse.clone()).as_io(), Some(&parse));
+    }
 }
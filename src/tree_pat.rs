@@ -0,0 +1,93 @@
+/// Builds a pattern matching a variant anywhere in a `treeerror!` tree, from the variants leading
+/// to it, for use in `match`, `matches!` and `if let`.
+///
+/// `tree_pat!(Root > Subtree > Leaf)` matches everything in `Leaf`, and patterns in parentheses
+/// after the last variant match its fields, starting with the payload: `Leaf(payload)`, or
+/// `Leaf(payload, location)` with `@location`. Fields left out are ignored, so the pattern
+/// doesn't change as extras are added to the tree. `@inline` subtrees are skipped, since their
+/// variants are in the enum holding them, and nodes behind a `#[cfg(...)]` can be matched when
+/// they're enabled where the tree is declared.
+///
+/// The root has to be named by the path it was declared at, which, like with `@mount`, needs
+/// `@export` on it if that's in another crate.
+/// ```
+/// use treeerror::{tree_pat, treeerror};
+///
+/// treeerror! {
+///     #![derive(Debug)]
+///
+///     Hello @location {
+///         OtherWorld {
+///             W0 @unit,
+///             W1 @unit,
+///         },
+///         Terminal(String),
+///         Unknown @flatunit,
+///     }
+/// }
+///
+/// let error = Hello::from(W0);
+/// assert!(matches!(error, tree_pat!(Hello > OtherWorld > W0)));
+/// assert!(!matches!(error, tree_pat!(Hello > OtherWorld > W1)));
+///
+/// match Hello::from("no such terminal".to_owned()) {
+///     tree_pat!(Hello > OtherWorld(world)) => panic!("expected `Terminal`, got {world:?}"),
+///     tree_pat!(Hello > Terminal(message)) => assert_eq!(message, "no such terminal"),
+///     tree_pat!(Hello > Unknown) => panic!("expected `Terminal`, got `Unknown`"),
+/// }
+/// ```
+#[macro_export]
+macro_rules! tree_pat {
+    ($($pat:tt)*) => {
+        $crate::expand_tree_pat! { $($pat)* }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    crate::treeerror! {
+        #![derive(Debug)]
+
+        Tree @names("{}Error") @modules @location @context {
+            Config {
+                NotFound @unit,
+                Parse(std::num::ParseIntError),
+            },
+            Net @inline {
+                Timeout @unit,
+                Io @shared(std::io::Error),
+            },
+            Unknown @flatunit,
+            #[cfg(all())]
+            Remote {
+                Refused @unit,
+            },
+        }
+    }
+
+    #[test]
+    fn test_tree_pat() {
+        use tree::{config, net, remote};
+
+        let (error, line) = (TreeError::from(config::NotFoundError), line!());
+        assert!(matches!(error, crate::tree_pat!(TreeError > Config > NotFound)));
+        assert!(matches!(error, crate::tree_pat!(self::TreeError > Config(config::ConfigError::NotFound(..)))));
+        assert!(!matches!(error, crate::tree_pat!(TreeError > Config > Parse)));
+        let crate::tree_pat!(TreeError > Config > NotFound(_, location, context)) = &error else {
+            panic!("expected `NotFound`, got {error:?}");
+        };
+        assert_eq!(location.line(), line);
+        assert!(context.is_empty());
+
+        let error = TreeError::from(std::io::Error::other("disconnected"));
+        if let crate::tree_pat!(TreeError > Io(io)) = &error {
+            assert_eq!(io.to_string(), "disconnected");
+        } else {
+            panic!("expected `Io`, got {error:?}");
+        }
+        assert!(matches!(TreeError::from(net::TimeoutError), crate::tree_pat!(TreeError > Timeout(net::TimeoutError))));
+        assert!(matches!(TreeError::Unknown, crate::tree_pat!(TreeError > Unknown)));
+        let refused = TreeError::from(remote::RefusedError);
+        assert!(matches!(refused, crate::tree_pat!(TreeError > Remote > Refused(remote::RefusedError))));
+    }
+}